@@ -1,8 +1,8 @@
 extern crate proc_macro;
-use proc_macro2::{Delimiter, Group, Literal, Punct, Spacing, TokenStream, TokenTree};
+use proc_macro2::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
 use proc_macro_error::proc_macro_error;
 use proc_macro_error::{abort, emit_error};
-use quote::quote;
+use quote::{format_ident, quote};
 
 #[proc_macro_error]
 #[proc_macro]
@@ -113,74 +113,150 @@ fn parse_keycode_group(input: TokenStream, out: &mut TokenStream) {
 }
 
 fn punctuation_to_keycode(p: &Punct, out: &mut TokenStream) {
-    match p.as_char() {
-        // Normal punctuation
-        '-' => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Minus), }),
-        '=' => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Equal), }),
-        ';' => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::SColon), }),
-        ',' => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Comma), }),
-        '.' => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Dot), }),
-        '/' => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Slash), }),
-
-        // Shifted punctuation
-        '!' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Kb1].as_slice()), }),
-        '@' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Kb2].as_slice()), }),
-        '#' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Kb3].as_slice()), }),
-        '$' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Kb4].as_slice()), }),
-        '%' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Kb5].as_slice()), }),
-        '^' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Kb6].as_slice()), }),
-        '&' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Kb7].as_slice()), }),
-        '*' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Kb8].as_slice()), }),
-        '_' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Minus].as_slice()), }),
-        '+' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Equal].as_slice()), }),
-        '|' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Bslash].as_slice()), }),
-        '~' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Grave].as_slice()), }),
-        '<' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Comma].as_slice()), }),
-        '>' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Dot].as_slice()), }),
-        '?' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Slash].as_slice()), }),
-        ':' => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::SColon].as_slice()), }),
+    match char_to_keycode(p.as_char()) {
+        Some((shifted, keycode)) => out.extend(keycode_action(shifted, &keycode)),
         // Is this reachable?
-        _ => emit_error!(p, "Punctuation could not be parsed as a keycode")
+        None => emit_error!(p, "Punctuation could not be parsed as a keycode"),
     }
 }
 
 fn literal_to_keycode(l: &Literal, out: &mut TokenStream) {
-    //let repr = l.to_string();
-    match l.to_string().as_str() {
-        "1" => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Kb1), }),
-        "2" => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Kb2), }),
-        "3" => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Kb3), }),
-        "4" => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Kb4), }),
-        "5" => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Kb5), }),
-        "6" => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Kb6), }),
-        "7" => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Kb7), }),
-        "8" => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Kb8), }),
-        "9" => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Kb9), }),
-        "0" => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Kb0), }),
+    let repr = l.to_string();
+    match repr.as_str() {
+        "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" | "0" => {
+            let (_, keycode) = char_to_keycode(repr.chars().next().unwrap()).unwrap();
+            out.extend(keycode_action(false, &keycode));
+        }
 
         // Char literals; mostly punctuation which can't be properly tokenized alone
-        r#"'\''"# => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Quote), }),
-        r#"'\\'"# => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Bslash), }),
-        // Shifted characters
-        "'['" => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::LBracket), }),
-        "']'" => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::RBracket), }),
-        "'`'" => out.extend(quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::Grave), }),
-        "'\"'" => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Quote].as_slice()), }),
-        "'('" => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Kb9].as_slice()), }),
-        "')'" => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Kb0].as_slice()), }),
-        "'{'" => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::LBracket].as_slice()), }),
-        "'}'" => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::RBracket].as_slice()), }),
-        "'_'" => out.extend(quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::Minus].as_slice()), }),
-
-        s if s.starts_with('\'') => emit_error!(l, "Literal could not be parsed as a keycode"; help = "Maybe try without quotes?"),
-
-        s if s.starts_with('\"')  => {
-            if s.len() == 3 {
-                emit_error!(l, "Typing strings on key press is not yet supported"; help = "Did you mean to use apostrophes instead of quotes?");
-            } else {
-                emit_error!(l, "Typing strings on key press is not yet supported");
+        s if s.starts_with('\'') => match unquote(s, '\'')
+            .as_deref()
+            .map(str::chars)
+            .map(|mut c| (c.next(), c.next()))
+        {
+            Some((Some(c), None)) if !c.is_alphanumeric() && c != ' ' => match char_to_keycode(c) {
+                Some((shifted, keycode)) => out.extend(keycode_action(shifted, &keycode)),
+                None => emit_error!(l, "Literal could not be parsed as a keycode"),
+            },
+            _ => {
+                emit_error!(l, "Literal could not be parsed as a keycode"; help = "Maybe try without quotes?")
             }
+        },
+
+        // String literals are typed on key press (Action::Sequence)
+        s if s.starts_with('"') || s.starts_with('r') => match unquote(s, '"') {
+            Some(string) => string_to_sequence(l, &string, out),
+            None => emit_error!(l, "String literal could not be parsed"),
+        },
+        _ => emit_error!(l, "Literal could not be parsed as a keycode"),
+    }
+}
+
+fn string_to_sequence(l: &Literal, string: &str, out: &mut TokenStream) {
+    let mut events = TokenStream::new();
+    for c in string.chars() {
+        match char_to_keycode(c) {
+            Some((false, keycode)) => events.extend(quote! {
+                keyberon::action::SequenceEvent::Tap(keyberon::key_code::KeyCode::#keycode),
+            }),
+            Some((true, keycode)) => events.extend(quote! {
+                keyberon::action::SequenceEvent::Press(keyberon::key_code::KeyCode::LShift),
+                keyberon::action::SequenceEvent::Tap(keyberon::key_code::KeyCode::#keycode),
+                keyberon::action::SequenceEvent::Release(keyberon::key_code::KeyCode::LShift),
+            }),
+            None => emit_error!(
+                l,
+                "Character {:?} can't be typed with a US QWERTY layout",
+                c
+            ),
+        }
+    }
+    out.extend(quote! { keyberon::action::Action::Sequence(&[#events].as_slice()), });
+}
+
+/// Returns the content of a char or string literal, unescaping it.
+fn unquote(repr: &str, quote: char) -> Option<String> {
+    if let Some(raw) = repr.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let raw = &raw[hashes..raw.len().checked_sub(hashes)?];
+        return Some(raw.strip_prefix(quote)?.strip_suffix(quote)?.to_string());
+    }
+    let inner = repr.strip_prefix(quote)?.strip_suffix(quote)?;
+    let mut res = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => res.push('\n'),
+            't' => res.push('\t'),
+            'r' => res.push('\r'),
+            '0' => res.push('\0'),
+            c @ ('\\' | '\'' | '"') => res.push(c),
+            _ => return None,
         }
-        _ => emit_error!(l, "Literal could not be parsed as a keycode")
     }
+    Some(res)
+}
+
+/// Generates the action typing the given keycode, with shift if needed.
+fn keycode_action(shifted: bool, keycode: &Ident) -> TokenStream {
+    if shifted {
+        quote! { keyberon::action::Action::MultipleKeyCodes(&[keyberon::key_code::KeyCode::LShift, keyberon::key_code::KeyCode::#keycode].as_slice()), }
+    } else {
+        quote! { keyberon::action::Action::KeyCode(keyberon::key_code::KeyCode::#keycode), }
+    }
+}
+
+/// The US QWERTY keycode typing the given character, and whether shift
+/// must be pressed.
+fn char_to_keycode(c: char) -> Option<(bool, Ident)> {
+    let (shifted, keycode) = match c {
+        'a'..='z' => return Some((false, format_ident!("{}", c.to_ascii_uppercase()))),
+        'A'..='Z' => return Some((true, format_ident!("{}", c))),
+        '1'..='9' | '0' => return Some((false, format_ident!("Kb{}", c))),
+
+        // Normal punctuation
+        ' ' => (false, "Space"),
+        '\n' => (false, "Enter"),
+        '\t' => (false, "Tab"),
+        '-' => (false, "Minus"),
+        '=' => (false, "Equal"),
+        ';' => (false, "SColon"),
+        ',' => (false, "Comma"),
+        '.' => (false, "Dot"),
+        '/' => (false, "Slash"),
+        '\'' => (false, "Quote"),
+        '\\' => (false, "Bslash"),
+        '[' => (false, "LBracket"),
+        ']' => (false, "RBracket"),
+        '`' => (false, "Grave"),
+
+        // Shifted punctuation
+        '!' => (true, "Kb1"),
+        '@' => (true, "Kb2"),
+        '#' => (true, "Kb3"),
+        '$' => (true, "Kb4"),
+        '%' => (true, "Kb5"),
+        '^' => (true, "Kb6"),
+        '&' => (true, "Kb7"),
+        '*' => (true, "Kb8"),
+        '(' => (true, "Kb9"),
+        ')' => (true, "Kb0"),
+        '_' => (true, "Minus"),
+        '+' => (true, "Equal"),
+        '|' => (true, "Bslash"),
+        '~' => (true, "Grave"),
+        '<' => (true, "Comma"),
+        '>' => (true, "Dot"),
+        '?' => (true, "Slash"),
+        ':' => (true, "SColon"),
+        '"' => (true, "Quote"),
+        '{' => (true, "LBracket"),
+        '}' => (true, "RBracket"),
+        _ => return None,
+    };
+    Some((shifted, Ident::new(keycode, Span::call_site())))
 }
@@ -1,6 +1,7 @@
 extern crate keyberon_macros;
 use keyberon::action::{k, l, m, Action, Action::*, HoldTapConfig, HoldTapAction};
 use keyberon::key_code::KeyCode::*;
+use keyberon::key_code::KeyCode;
 use keyberon::layout::*;
 use keyberon_macros::layout;
 
@@ -71,6 +72,7 @@ fn test_nesting() {
 }
 
 #[test]
+#[allow(clippy::precedence)]
 fn test_layer_switch() {
     static A: Layers<5, 1, 1> = layout! {
         {
@@ -108,3 +110,30 @@ fn test_keycode_group_comma() {
     static B: Layers<3, 1, 1> = [[[k(C), Action::MultipleActions(&[k(D), k(E)].as_slice()), k(F)]]];
     assert_eq!(A, B);
 }
+
+#[test]
+fn test_string() {
+    use keyberon::action::SequenceEvent::*;
+    static A: Layers<2, 1, 1> = layout! {
+        {
+            ["Hi!\n" r"a\"]
+        }
+    };
+    static B: Layers<2, 1, 1> = [[[
+        Action::Sequence(
+            &[
+                Press(LShift),
+                Tap(H),
+                Release(LShift),
+                Tap(I),
+                Press(LShift),
+                Tap(Kb1),
+                Release(LShift),
+                Tap(Enter),
+            ]
+            .as_slice(),
+        ),
+        Action::Sequence(&[Tap(KeyCode::A), Tap(Bslash)].as_slice()),
+    ]]];
+    assert_eq!(A, B);
+}
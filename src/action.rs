@@ -1,7 +1,10 @@
 //! The different actions that can be done.
 
+use crate::consumer::ConsumerUsage;
 use crate::key_code::KeyCode;
 use crate::layout::{StackedIter, WaitingAction};
+use crate::mouse::MouseKey;
+use crate::system::SystemUsage;
 use core::fmt::Debug;
 
 /// Behavior configuration of HoldTap.
@@ -114,7 +117,11 @@ impl PartialEq for HoldTapConfig {
             | (HoldTapConfig::HoldOnOtherKeyPress, HoldTapConfig::HoldOnOtherKeyPress)
             | (HoldTapConfig::PermissiveHold, HoldTapConfig::PermissiveHold) => true,
             (HoldTapConfig::Custom(self_func), HoldTapConfig::Custom(other_func)) => {
-                *self_func as fn(StackedIter<'static>) -> Option<WaitingAction> == *other_func
+                // `core::ptr::fn_addr_eq` would need rust 1.85
+                #[allow(unknown_lints, unpredictable_function_pointer_comparisons)]
+                let eq =
+                    *self_func as fn(StackedIter<'static>) -> Option<WaitingAction> == *other_func;
+                eq
             }
             _ => false,
         }
@@ -169,6 +176,170 @@ where
     pub tap_hold_interval: u16,
 }
 
+/// Activate an action for the next key press only.
+///
+/// When the key is tapped, `action` stays active after the release
+/// of the key, until another key is pressed. It is then released on
+/// the next tick, i.e. just after the report containing the pressed
+/// key. Mostly used with a modifier or a `Layer` action, allowing to
+/// type a capital letter without holding shift.
+///
+/// If no other key is pressed during `timeout` ticks (usually
+/// milliseconds) after the press, `action` is released. If another key
+/// is pressed while the one-shot key is still held, it behaves as a
+/// classic key: `action` is released with the key.
+///
+/// Pressing a `Layer`, `HoldTap` or another `OneShot` key, or only
+/// modifiers, doesn't release the pending one-shot actions, allowing
+/// them to be combined (see
+/// [`Layout::set_modifier_predicate`](crate::layout::Layout::set_modifier_predicate)).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct OneShotAction<T, K>
+where
+    T: 'static,
+    K: 'static,
+{
+    /// The action activated until the next key press.
+    pub action: Action<T, K>,
+    /// The duration, in ticks (usually milliseconds), during which
+    /// the action waits for another key press.
+    pub timeout: u16,
+}
+
+/// Perform different actions depending on the number of consecutive
+/// taps of the key.
+///
+/// The dance ends when the key is not pressed again in less than
+/// `timeout` ticks (usually milliseconds) after its release, when
+/// another key is pressed or released, or when the maximum number of
+/// taps is reached. The tap action corresponding to the number of
+/// taps is then performed. If the key is held more than `timeout`
+/// ticks, the hold action corresponding to the number of taps is
+/// performed instead.
+///
+/// For example, with `taps: &[k(A), k(B)]` and `holds: &[k(LCtrl)]`,
+/// a tap gives `A`, a double tap gives `B`, and a held key gives
+/// `LCtrl`. A tap followed by a held key gives a held `B`, as there is
+/// no second hold action.
+///
+/// In a `MultipleActions` action, the following taps can't be
+/// awaited: the first tap action is performed, as a classic key.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TapDanceAction<T, K>
+where
+    T: 'static,
+    K: 'static,
+{
+    /// The duration, in ticks (usually milliseconds), to wait for the
+    /// next press or release of the key.
+    pub timeout: u16,
+    /// The actions performed after 1, 2, 3… taps.
+    pub taps: &'static [Action<T, K>],
+    /// The actions performed when the key is held after 0, 1, 2…
+    /// taps. If missing, the corresponding tap action is held.
+    pub holds: &'static [Action<T, K>],
+}
+
+/// A step of a key sequence (see [`Action::Sequence`]).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SequenceEvent<K: 'static> {
+    /// Press the given key code.
+    Press(K),
+    /// Release the given key code.
+    Release(K),
+    /// Press the given key code, and release it on the next tick.
+    Tap(K),
+    /// Do nothing during the given number of ticks (usually
+    /// milliseconds).
+    Delay(u16),
+}
+
+/// Capture the following key presses and perform the action
+/// corresponding to the typed sequence of key codes.
+///
+/// After the press of the leader key, the presses of the keys are
+/// captured: they are not sent to the computer. `Layer`,
+/// `ToggleLayer` and `LayerLock` actions are not captured, allowing to
+/// use several layers in a sequence. For a `HoldTap` action, the key
+/// code of the tap action is captured. Any other action ends the
+/// capture and is performed as usual.
+///
+/// When the captured key codes match a sequence, the corresponding
+/// action is tapped, i.e. pressed and released on the next tick. If
+/// the sequence is also the beginning of a longer sequence, the
+/// action is only performed at the end of the timeout. The capture is
+/// abandoned if no sequence starts with the captured key codes, or if
+/// no sequence matches at the end of the timeout. A sequence can't be
+/// longer than 8 key codes.
+///
+/// # Example
+///
+/// ```
+/// use keyberon::action::{k, Action, LeaderAction};
+/// use keyberon::key_code::KeyCode::*;
+///
+/// static LEADER: Action = Action::Leader(&LeaderAction {
+///     timeout: 1000,
+///     sequences: &[
+///         (&[E, M], Action::Sequence(&[].as_slice())),
+///         (&[C], k(Copy)),
+///         (&[C, W], k(Cut)),
+///     ],
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct LeaderAction<T, K>
+where
+    T: 'static,
+    K: 'static,
+{
+    /// The duration, in ticks (usually milliseconds), of the capture,
+    /// starting from the press of the leader key.
+    pub timeout: u16,
+    /// The sequences of key codes and their corresponding action.
+    pub sequences: &'static [(&'static [K], Action<T, K>)],
+}
+
+/// Configuration of the caps word mode (see [`Action::CapsWord`]).
+///
+/// While the mode is active, `shift` is added to the report when a key
+/// code of `shifted` is held. Pressing any key code that is neither in
+/// `shifted` nor in `continue_with` ends the mode. The mode also ends
+/// if no key code is pressed during `timeout` ticks (usually
+/// milliseconds).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CapsWordConfig<K: 'static> {
+    /// The duration, in ticks (usually milliseconds), after which the
+    /// mode ends if no key code is pressed.
+    pub timeout: u16,
+    /// The key code added to the report to shift the key codes.
+    pub shift: K,
+    /// The key codes that are shifted, usually the letters.
+    pub shifted: &'static [K],
+    /// The key codes that are not shifted but don't end the mode,
+    /// usually the digits, the backspace and the shift keys.
+    pub continue_with: &'static [K],
+}
+
+impl CapsWordConfig<KeyCode> {
+    /// The usual configuration for a QWERTY layout, with a 5 seconds
+    /// timeout (considering a tick per millisecond).
+    pub const DEFAULT: Self = {
+        use KeyCode::*;
+        Self {
+            timeout: 5000,
+            shift: LShift,
+            shifted: &[
+                A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+            ],
+            continue_with: &[
+                Kb1, Kb2, Kb3, Kb4, Kb5, Kb6, Kb7, Kb8, Kb9, Kb0, Minus, BSpace, Delete, LShift,
+                RShift,
+            ],
+        }
+    };
+}
+
 /// The different actions that can be done.
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -180,7 +351,9 @@ where
     /// No operation action: just do nothing.
     NoOp,
     /// Transparent, i.e. get the action from the default layer. On
-    /// the default layer, it is equivalent to `NoOp`.
+    /// the default layer, it is equivalent to `NoOp`. The layers below
+    /// the current one can be used instead, see
+    /// [`TransResolution`](crate::layout::TransResolution).
     Trans,
     /// A key code, i.e. a classic key.
     KeyCode(K),
@@ -194,10 +367,54 @@ where
     /// Fn key. If several layer actions are hold at the same time,
     /// the last pressed defines the current layer.
     Layer(usize),
+    /// Activate the layer if it is not active, deactivate it
+    /// otherwise. A toggled layer stays active after the release of
+    /// the key. As for held layers, the last activated layer defines
+    /// the current layer.
+    ToggleLayer(usize),
+    /// Keep the current layer active after the release of the key
+    /// that activated it. If the current layer is already locked, it
+    /// is unlocked.
+    LayerLock,
     /// Change the default layer.
     DefaultLayer(usize),
     /// Perform different actions on key hold/tap (see [`HoldTapAction`]).
     HoldTap(&'static HoldTapAction<T, K>),
+    /// Activate an action for the next key press only (see
+    /// [`OneShotAction`]).
+    OneShot(&'static OneShotAction<T, K>),
+    /// Perform different actions depending on the number of
+    /// consecutive taps (see [`TapDanceAction`]).
+    TapDance(&'static TapDanceAction<T, K>),
+    /// Play a sequence of key events, one step per tick, allowing to
+    /// type a string or a chain of shortcuts with a single key press.
+    ///
+    /// The sequence is played until the end, even if the key is
+    /// released before. The key codes still pressed at the end of the
+    /// sequence are released. If a sequence is already playing, the
+    /// new one is played after it. At most
+    /// [`SEQUENCE_QUEUE_SIZE`](crate::layout::SEQUENCE_QUEUE_SIZE)
+    /// sequences are queued, the following ones being ignored.
+    Sequence(&'static &'static [SequenceEvent<K>]),
+    /// Perform an action depending on the sequence of the following
+    /// key presses (see [`LeaderAction`]).
+    Leader(&'static LeaderAction<T, K>),
+    /// A mouse key: a button, a cursor movement or a scroll. The
+    /// movements accelerate while the key is held (see
+    /// [`MouseConfig`](crate::mouse::MouseConfig)).
+    Mouse(MouseKey),
+    /// A consumer control usage, i.e. a media key. It is sent using
+    /// the consumer control report (see the
+    /// [`consumer`](crate::consumer) module).
+    Consumer(ConsumerUsage),
+    /// A system control usage, i.e. a power, sleep or wake up key.
+    /// It is sent using the system control report (see the
+    /// [`system`](crate::system) module).
+    System(SystemUsage),
+    /// Shift the letters until the end of the current word, i.e.
+    /// until a word breaking key is pressed (see
+    /// [`CapsWordConfig`]). Pressing the key again ends the mode.
+    CapsWord(&'static CapsWordConfig<K>),
     /// Custom action.
     ///
     /// Define a user defined action. This enum can be anything you
@@ -0,0 +1,310 @@
+//! Composite HID class: keyboard, mouse and consumer control.
+//!
+//! Each device uses its own interface and interrupt IN endpoint, the
+//! host seeing them as independent HID devices.
+
+use crate::consumer::Consumer;
+use crate::hid::{self, HidClass, ProtocolMode};
+use crate::key_code::NkroKbHidReport;
+use crate::keyboard::{Keyboard, Leds};
+use crate::mouse::Mouse;
+use usb_device::bus::{StringIndex, UsbBus, UsbBusAllocator};
+use usb_device::class::{ControlIn, ControlOut, UsbClass};
+use usb_device::descriptor::{lang_id::LangID, DescriptorWriter};
+use usb_device::endpoint::EndpointAddress;
+
+/// A USB class grouping a keyboard, a mouse and a consumer control
+/// device, with an optional N-key rollover keyboard.
+pub struct CompositeClass<'a, B: UsbBus, L: Leds> {
+    keyboard: HidClass<'a, B, Keyboard<L>>,
+    nkro: Option<HidClass<'a, B, Keyboard<()>>>,
+    mouse: HidClass<'a, B, Mouse>,
+    consumer: HidClass<'a, B, Consumer>,
+}
+
+impl<'a, B: UsbBus, L: Leds> CompositeClass<'a, B, L> {
+    /// Creates a new `CompositeClass` object.
+    pub fn new(keyboard: Keyboard<L>, alloc: &'a UsbBusAllocator<B>) -> Self {
+        CompositeClass {
+            keyboard: HidClass::new(keyboard, alloc),
+            nkro: None,
+            mouse: HidClass::new(Mouse::new(), alloc),
+            consumer: HidClass::new(Consumer::new(), alloc),
+        }
+    }
+    /// Creates a new `CompositeClass` object with an additional N-key
+    /// rollover keyboard.
+    ///
+    /// As done by QMK, the N-key rollover keyboard uses its own non
+    /// boot interface, after the 6 key rollover boot keyboard
+    /// `keyboard`. The reports are sent using
+    /// [`push_nkro_report`](CompositeClass::push_nkro_report), and the
+    /// LED state sent to any of the keyboards is given to the `Leds`
+    /// of `keyboard`.
+    pub fn new_nkro(keyboard: Keyboard<L>, alloc: &'a UsbBusAllocator<B>) -> Self {
+        let keyboard = HidClass::new(keyboard, alloc);
+        let nkro = HidClass::new(Keyboard::new_nkro(()), alloc);
+        CompositeClass {
+            keyboard,
+            nkro: Some(nkro),
+            mouse: HidClass::new(Mouse::new(), alloc),
+            consumer: HidClass::new(Consumer::new(), alloc),
+        }
+    }
+    /// Returns the keyboard class.
+    pub fn keyboard_mut(&mut self) -> &mut HidClass<'a, B, Keyboard<L>> {
+        &mut self.keyboard
+    }
+    /// Returns the N-key rollover keyboard class, if any.
+    pub fn nkro_mut(&mut self) -> Option<&mut HidClass<'a, B, Keyboard<()>>> {
+        self.nkro.as_mut()
+    }
+    /// Returns the mouse class.
+    pub fn mouse_mut(&mut self) -> &mut HidClass<'a, B, Mouse> {
+        &mut self.mouse
+    }
+    /// Returns the consumer control class.
+    pub fn consumer_mut(&mut self) -> &mut HidClass<'a, B, Consumer> {
+        &mut self.consumer
+    }
+    /// Sets the current N-key rollover keyboard HID report and, if it
+    /// is modified, queues it.
+    ///
+    /// The report is sent by the N-key rollover keyboard. Without it,
+    /// or when the host selects the boot protocol on the boot keyboard
+    /// (as a BIOS), the 6 key rollover report is sent by the boot
+    /// keyboard instead.
+    pub fn push_nkro_report(&mut self, report: NkroKbHidReport) -> Result<(), hid::Error> {
+        match &mut self.nkro {
+            Some(nkro) if self.keyboard.protocol_mode() == ProtocolMode::Report => {
+                nkro.push_nkro_report(report)
+            }
+            _ => self.keyboard.push_keyboard_report(report.to_boot_report()),
+        }
+    }
+    fn nkro_interface(&self) -> Option<u16> {
+        self.nkro.as_ref().map(|nkro| nkro.interface_index())
+    }
+    fn class_mut(&mut self, interface: u16) -> Option<&mut dyn UsbClass<B>> {
+        if interface == self.keyboard.interface_index() {
+            Some(&mut self.keyboard)
+        } else if Some(interface) == self.nkro_interface() {
+            self.nkro.as_mut().map(|nkro| nkro as &mut dyn UsbClass<B>)
+        } else if interface == self.mouse.interface_index() {
+            Some(&mut self.mouse)
+        } else if interface == self.consumer.interface_index() {
+            Some(&mut self.consumer)
+        } else {
+            None
+        }
+    }
+    fn classes_mut(&mut self) -> [Option<&mut dyn UsbClass<B>>; 4] {
+        [
+            Some(&mut self.keyboard),
+            self.nkro.as_mut().map(|nkro| nkro as &mut dyn UsbClass<B>),
+            Some(&mut self.mouse),
+            Some(&mut self.consumer),
+        ]
+    }
+}
+
+impl<B: UsbBus, L: Leds> UsbClass<B> for CompositeClass<'_, B, L> {
+    fn poll(&mut self) {
+        for class in self.classes_mut().iter_mut().flatten() {
+            class.poll();
+        }
+    }
+
+    fn reset(&mut self) {
+        for class in self.classes_mut().iter_mut().flatten() {
+            class.reset();
+        }
+    }
+
+    fn get_configuration_descriptors(
+        &self,
+        writer: &mut DescriptorWriter,
+    ) -> usb_device::Result<()> {
+        self.keyboard.get_configuration_descriptors(writer)?;
+        if let Some(nkro) = &self.nkro {
+            nkro.get_configuration_descriptors(writer)?;
+        }
+        self.mouse.get_configuration_descriptors(writer)?;
+        self.consumer.get_configuration_descriptors(writer)
+    }
+
+    fn get_string(&self, index: StringIndex, lang_id: LangID) -> Option<&str> {
+        self.keyboard
+            .get_string(index, lang_id)
+            .or_else(|| self.nkro.as_ref()?.get_string(index, lang_id))
+            .or_else(|| self.mouse.get_string(index, lang_id))
+            .or_else(|| self.consumer.get_string(index, lang_id))
+    }
+
+    fn endpoint_in_complete(&mut self, addr: EndpointAddress) {
+        for class in self.classes_mut().iter_mut().flatten() {
+            class.endpoint_in_complete(addr);
+        }
+    }
+
+    fn endpoint_out(&mut self, addr: EndpointAddress) {
+        for class in self.classes_mut().iter_mut().flatten() {
+            class.endpoint_out(addr);
+        }
+    }
+
+    fn control_in(&mut self, xfer: ControlIn<B>) {
+        if let Some(class) = self.class_mut(xfer.request().index) {
+            class.control_in(xfer);
+        }
+    }
+
+    fn control_out(&mut self, xfer: ControlOut<B>) {
+        let interface = xfer.request().index;
+        if let Some(class) = self.class_mut(interface) {
+            class.control_out(xfer);
+        }
+        // the LED state sent to the N-key rollover keyboard is given to
+        // the boot keyboard
+        if let Some(nkro) = &mut self.nkro {
+            let leds = nkro.device_mut().leds_report();
+            let keyboard = self.keyboard.device_mut();
+            if interface == nkro.interface_index() && leds != keyboard.leds_report() {
+                keyboard.set_leds_report(leds);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::hid::test::{control, control_with_data, setup, MockBus};
+    use crate::hid::{ProtocolMode, Request};
+    use usb_device::prelude::*;
+
+    #[test]
+    fn descriptors() {
+        let alloc = UsbBusAllocator::new(MockBus::default());
+        let mut class = CompositeClass::new(Keyboard::new(()), &alloc);
+        let mut device = UsbDeviceBuilder::new(&alloc, UsbVidPid(0, 0)).build();
+        let get_configuration = [0x80, 0x06, 0, 2, 0, 0, 0xff, 0];
+        let descriptor = control(&mut device, &mut class, get_configuration).unwrap();
+        assert_eq!(descriptor.len(), descriptor[2] as usize);
+
+        let mut interfaces = std::vec![];
+        let mut endpoints = std::vec![];
+        let mut i = 0;
+        while i < descriptor.len() {
+            match descriptor[i + 1] {
+                0x04 => interfaces.push(descriptor[i + 2]),
+                0x05 => endpoints.push(descriptor[i + 2]),
+                _ => {}
+            }
+            i += descriptor[i] as usize;
+        }
+        assert_eq!(std::vec![0, 1, 2], interfaces);
+        assert_eq!(std::vec![0x81, 0x82, 0x83], endpoints);
+    }
+
+    #[test]
+    fn control_routing() {
+        let alloc = UsbBusAllocator::new(MockBus::default());
+        let mut class = CompositeClass::new(Keyboard::new(()), &alloc);
+        let mut device = UsbDeviceBuilder::new(&alloc, UsbVidPid(0, 0)).build();
+        let get_protocol = |interface| setup(0xa1, Request::GetProtocol, 0, interface, 1);
+
+        // the boot protocol is only selected on the mouse interface
+        let set_boot = setup(0x21, Request::SetProtocol, 0, 1, 0);
+        assert_eq!(
+            Some(std::vec![]),
+            control(&mut device, &mut class, set_boot)
+        );
+        assert_eq!(ProtocolMode::Report, class.keyboard_mut().protocol_mode());
+        assert_eq!(ProtocolMode::Boot, class.mouse_mut().protocol_mode());
+        assert_eq!(ProtocolMode::Report, class.consumer_mut().protocol_mode());
+        assert_eq!(
+            Some(std::vec![1]),
+            control(&mut device, &mut class, get_protocol(0))
+        );
+        assert_eq!(
+            Some(std::vec![0]),
+            control(&mut device, &mut class, get_protocol(1))
+        );
+        assert_eq!(
+            Some(std::vec![1]),
+            control(&mut device, &mut class, get_protocol(2))
+        );
+
+        // no class handles the other interfaces
+        assert_eq!(None, control(&mut device, &mut class, get_protocol(3)));
+    }
+
+    #[test]
+    fn nkro() {
+        use crate::key_code::KeyCode::*;
+
+        let alloc = UsbBusAllocator::new(MockBus::default());
+        let mut class = CompositeClass::new_nkro(Keyboard::new(()), &alloc);
+        let mut device = UsbDeviceBuilder::new(&alloc, UsbVidPid(0, 0)).build();
+        let get_configuration = [0x80, 0x06, 0, 2, 0, 0, 0xff, 0];
+        let descriptor = control(&mut device, &mut class, get_configuration).unwrap();
+
+        // (number, subclass, protocol) of the interfaces, and (address,
+        // max packet size) of the endpoints
+        let mut interfaces = std::vec![];
+        let mut endpoints = std::vec![];
+        let mut i = 0;
+        while i < descriptor.len() {
+            let d = &descriptor[i..];
+            match d[1] {
+                0x04 => interfaces.push((d[2], d[6], d[7])),
+                0x05 => endpoints.push((d[2], d[4])),
+                _ => {}
+            }
+            i += d[0] as usize;
+        }
+        assert_eq!(
+            std::vec![(0, 1, 1), (1, 0, 0), (2, 1, 2), (3, 0, 0)],
+            interfaces
+        );
+        assert_eq!(
+            std::vec![(0x81, 8), (0x82, 32), (0x83, 8), (0x84, 8)],
+            endpoints
+        );
+
+        // the N-key rollover report is sent by default
+        let report: NkroKbHidReport = [A, B].iter().copied().collect();
+        assert!(class.push_nkro_report(report.clone()).is_ok());
+        let bus = device.bus();
+        assert_eq!(
+            std::vec![(EndpointAddress::from(0x82), report.as_bytes().into())],
+            bus.take_written()
+        );
+
+        // the boot keyboard is used with the boot protocol
+        let set_boot = setup(0x21, Request::SetProtocol, 0, 0, 0);
+        assert_eq!(
+            Some(std::vec![]),
+            control(&mut device, &mut class, set_boot)
+        );
+        let report: NkroKbHidReport = [A, B, C].iter().copied().collect();
+        assert!(class.push_nkro_report(report).is_ok());
+        assert_eq!(
+            std::vec![(
+                EndpointAddress::from(0x81),
+                std::vec![0, 0, 4, 5, 6, 0, 0, 0]
+            )],
+            device.bus().take_written()
+        );
+
+        // the LED state sent to the N-key rollover keyboard
+        let set_leds = setup(0x21, Request::SetReport, 0x0200, 1, 1);
+        assert_eq!(
+            Some(std::vec![]),
+            control_with_data(&mut device, &mut class, set_leds, &[2])
+        );
+        assert_eq!(2, class.keyboard_mut().device_mut().leds_report());
+    }
+}
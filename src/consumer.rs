@@ -0,0 +1,232 @@
+//! Consumer control (media keys) HID device implementation.
+//!
+//! The media key codes of the keyboard usage page (as
+//! `KeyCode::MediaPlayPause`) are ignored by most of the operating
+//! systems. The consumer control usage page is the standard way to
+//! control the media playback, the volume or to launch applications.
+
+use crate::hid::{self, HidDevice, Protocol, ReportType, Subclass};
+use crate::key_code::KeyCode;
+
+/// A consumer control usage (usage page 0x0C).
+///
+/// Only the most common usages are defined.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(u16)]
+pub enum ConsumerUsage {
+    /// Increase the screen brightness.
+    BrightnessUp = 0x6F,
+    /// Decrease the screen brightness.
+    BrightnessDown = 0x70,
+    /// Play.
+    Play = 0xB0,
+    /// Pause.
+    Pause = 0xB1,
+    /// Record.
+    Record = 0xB2,
+    /// Fast forward.
+    FastForward = 0xB3,
+    /// Rewind.
+    Rewind = 0xB4,
+    /// Next track.
+    ScanNextTrack = 0xB5,
+    /// Previous track.
+    ScanPreviousTrack = 0xB6,
+    /// Stop.
+    Stop = 0xB7,
+    /// Eject.
+    Eject = 0xB8,
+    /// Play or pause.
+    PlayPause = 0xCD,
+    /// Mute.
+    Mute = 0xE2,
+    /// Increase the volume.
+    VolumeIncrement = 0xE9,
+    /// Decrease the volume.
+    VolumeDecrement = 0xEA,
+    /// Launch the media player.
+    AlConsumerControlConfiguration = 0x183,
+    /// Launch the text editor.
+    AlTextEditor = 0x185,
+    /// Launch the email reader.
+    AlEmailReader = 0x18A,
+    /// Launch the calculator.
+    AlCalculator = 0x192,
+    /// Launch the file browser.
+    AlLocalMachineBrowser = 0x194,
+    /// Launch the internet browser.
+    AlInternetBrowser = 0x196,
+    /// Lock the screen.
+    AlTerminalLockScreensaver = 0x19E,
+    /// Search.
+    AcSearch = 0x221,
+    /// Go to the home page.
+    AcHome = 0x223,
+    /// Go back.
+    AcBack = 0x224,
+    /// Go forward.
+    AcForward = 0x225,
+    /// Stop loading.
+    AcStop = 0x226,
+    /// Refresh.
+    AcRefresh = 0x227,
+    /// Open the bookmarks.
+    AcBookmarks = 0x22A,
+    /// Scroll up.
+    AcScrollUp = 0x233,
+    /// Scroll down.
+    AcScrollDown = 0x234,
+}
+
+impl ConsumerUsage {
+    /// Returns the consumer usage corresponding to a media key code
+    /// of the keyboard usage page, if any.
+    ///
+    /// `KeyCode::MediaSleep` has no consumer usage, see
+    /// [`SystemUsage`](crate::system::SystemUsage).
+    pub fn from_media_key_code(kc: KeyCode) -> Option<Self> {
+        use ConsumerUsage::*;
+        use KeyCode::*;
+        Some(match kc {
+            MediaPlayPause => PlayPause,
+            MediaStopCD => ConsumerUsage::Stop,
+            MediaPreviousSong => ScanPreviousTrack,
+            MediaNextSong => ScanNextTrack,
+            MediaEjectCD => Eject,
+            MediaVolUp => VolumeIncrement,
+            MediaVolDown => VolumeDecrement,
+            MediaMute => ConsumerUsage::Mute,
+            MediaWWW => AlInternetBrowser,
+            MediaBack => AcBack,
+            MediaForward => AcForward,
+            MediaStop => AcStop,
+            MediaFind => AcSearch,
+            MediaScrollUp => AcScrollUp,
+            MediaScrollDown => AcScrollDown,
+            MediaEdit => AlTextEditor,
+            MediaCoffee => AlTerminalLockScreensaver,
+            MediaRefresh => AcRefresh,
+            MediaCalc => AlCalculator,
+            _ => return None,
+        })
+    }
+}
+
+/// A consumer control USB HID report.
+///
+/// It can handle 4 usages at the same time.
+#[derive(Default, Debug, Clone, Eq, PartialEq)]
+pub struct ConsumerReport([u8; 8]);
+
+impl core::iter::FromIterator<ConsumerUsage> for ConsumerReport {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = ConsumerUsage>,
+    {
+        let mut res = Self::default();
+        for usage in iter {
+            res.pressed(usage);
+        }
+        res
+    }
+}
+
+impl ConsumerReport {
+    /// Returns the byte slice corresponding to the report.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Add the given usage to the report. If the report is full, the
+    /// usage is ignored.
+    pub fn pressed(&mut self, usage: ConsumerUsage) {
+        let usage = (usage as u16).to_le_bytes();
+        if let Some(c) = self.0.chunks_exact_mut(2).find(|c| c == &[0, 0]) {
+            c.copy_from_slice(&usage);
+        }
+    }
+}
+
+#[rustfmt::skip]
+const REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0C,        // Usage Page (Consumer)
+    0x09, 0x01,        // Usage (Consumer Control)
+    0xA1, 0x01,        // Collection (Application)
+    0x19, 0x00,        //   Usage Minimum (Unassigned)
+    0x2A, 0xFF, 0x03,  //   Usage Maximum (0x03FF)
+    0x15, 0x00,        //   Logical Minimum (0)
+    0x26, 0xFF, 0x03,  //   Logical Maximum (1023)
+    0x95, 0x04,        //   Report Count (4)
+    0x75, 0x10,        //   Report Size (16)
+    0x81, 0x00,        //   Input (Data,Array,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    0xC0,              // End Collection
+];
+
+/// A consumer control HID device.
+pub struct Consumer {
+    report: ConsumerReport,
+}
+
+impl Default for Consumer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Consumer {
+    /// Creates a new `Consumer` object.
+    pub fn new() -> Consumer {
+        Consumer {
+            report: ConsumerReport::default(),
+        }
+    }
+    /// Set the current consumer control HID report. Returns `true`
+    /// if it is modified.
+    pub fn set_consumer_report(&mut self, report: ConsumerReport) -> bool {
+        if report == self.report {
+            false
+        } else {
+            self.report = report;
+            true
+        }
+    }
+}
+
+impl HidDevice for Consumer {
+    fn subclass(&self) -> Subclass {
+        Subclass::None
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::None
+    }
+
+    fn max_packet_size(&self) -> u16 {
+        8
+    }
+
+    fn report_descriptor(&self) -> &[u8] {
+        REPORT_DESCRIPTOR
+    }
+
+    fn interface_name(&self) -> Option<&str> {
+        Some("Consumer Control")
+    }
+
+    fn get_report(&mut self, report_type: ReportType, _report_id: u8) -> Result<&[u8], hid::Error> {
+        match report_type {
+            ReportType::Input => Ok(self.report.as_bytes()),
+            _ => Err(hid::Error),
+        }
+    }
+
+    fn set_report(
+        &mut self,
+        _report_type: ReportType,
+        _report_id: u8,
+        _data: &[u8],
+    ) -> Result<(), hid::Error> {
+        Err(hid::Error)
+    }
+}
@@ -7,12 +7,16 @@ use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus, UsbBusAllocator};
 use usb_device::class::{ControlIn, ControlOut, UsbClass};
 use usb_device::control;
 use usb_device::control::{Recipient, RequestType};
-use usb_device::descriptor::{DescriptorWriter, lang_id::LangID};
-use usb_device::endpoint::{EndpointAddress, EndpointIn};
+use usb_device::descriptor::{lang_id::LangID, DescriptorWriter};
+use usb_device::device::DEFAULT_ALTERNATE_SETTING;
+use usb_device::endpoint::{EndpointAddress, EndpointIn, EndpointOut};
 use usb_device::UsbError;
 
+use heapless::{Deque, Vec};
+
 const SPECIFICATION_RELEASE: u16 = 0x111;
 const INTERFACE_CLASS_HID: u8 = 0x03;
+const REPORT_QUEUE_SIZE: usize = 8;
 
 pub struct Error;
 
@@ -64,6 +68,61 @@ impl Request {
     }
 }
 
+/// The country code of the HID descriptor, i.e. the localization of
+/// the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CountryCode {
+    NotSupported = 0,
+    Arabic = 1,
+    Belgian = 2,
+    CanadianBilingual = 3,
+    CanadianFrench = 4,
+    CzechRepublic = 5,
+    Danish = 6,
+    Finnish = 7,
+    French = 8,
+    German = 9,
+    Greek = 10,
+    Hebrew = 11,
+    Hungary = 12,
+    International = 13,
+    Italian = 14,
+    JapanKatakana = 15,
+    Korean = 16,
+    LatinAmerican = 17,
+    NetherlandsDutch = 18,
+    Norwegian = 19,
+    PersianFarsi = 20,
+    Poland = 21,
+    Portuguese = 22,
+    Russia = 23,
+    Slovakia = 24,
+    Spanish = 25,
+    Swedish = 26,
+    SwissFrench = 27,
+    SwissGerman = 28,
+    Switzerland = 29,
+    Taiwan = 30,
+    TurkishQ = 31,
+    Uk = 32,
+    Us = 33,
+    Yugoslavia = 34,
+    TurkishF = 35,
+}
+
+/// The protocol used by the host, selected by a `SetProtocol` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ProtocolMode {
+    /// The boot protocol, used by the BIOS: the report descriptor is
+    /// ignored and the boot report format is expected.
+    Boot = 0x00,
+    /// The report protocol, using the report descriptor. This is the
+    /// protocol used after a reset.
+    Report = 0x01,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ReportType {
     Input,
@@ -100,33 +159,94 @@ pub trait HidDevice {
     ) -> Result<(), Error>;
 
     fn get_report(&mut self, report_type: ReportType, report_id: u8) -> Result<&[u8], Error>;
+
+    /// Returns the name of the interface, displayed by the host.
+    fn interface_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the country code of the HID descriptor.
+    fn country_code(&self) -> CountryCode {
+        CountryCode::NotSupported
+    }
+
+    /// Returns the maximum packet size of the interrupt OUT endpoint,
+    /// or `None` if the device doesn't use one. Without interrupt OUT
+    /// endpoint, the output reports are received using `set_report`.
+    fn out_max_packet_size(&self) -> Option<u16> {
+        None
+    }
+
+    /// Called when an output report is received on the interrupt OUT
+    /// endpoint. The returned input report, if any, is queued as an
+    /// answer.
+    fn out_report(&mut self, _data: &[u8]) -> Option<&[u8]> {
+        None
+    }
+
+    /// Called when the host selects the protocol, and on reset. A
+    /// device with a non boot report descriptor must then switch its
+    /// report format.
+    fn set_protocol(&mut self, _mode: ProtocolMode) {}
 }
 
 pub struct HidClass<'a, B: UsbBus, D: HidDevice> {
     device: D,
     interface: InterfaceNumber,
+    interface_string: Option<StringIndex>,
     endpoint_interrupt_in: EndpointIn<'a, B>,
+    endpoint_interrupt_out: Option<EndpointOut<'a, B>>,
     expect_interrupt_in_complete: bool,
+    protocol: ProtocolMode,
+    idle_rate: u8,
+    last_report: Vec<u8, 64>,
+    since_last_report: u32,
+    queue: Deque<Vec<u8, 64>, REPORT_QUEUE_SIZE>,
+    suspended: bool,
 }
 
 impl<B: UsbBus, D: HidDevice> HidClass<'_, B, D> {
     pub fn new(device: D, alloc: &UsbBusAllocator<B>) -> HidClass<'_, B, D> {
         let max_packet_size = device.max_packet_size();
+        let out_max_packet_size = device.out_max_packet_size();
+        let interface_string = device.interface_name().is_some();
         HidClass {
             device,
             interface: alloc.interface(),
+            interface_string: interface_string.then(|| alloc.string()),
             endpoint_interrupt_in: alloc.interrupt(max_packet_size, 10),
+            endpoint_interrupt_out: out_max_packet_size.map(|size| alloc.interrupt(size, 10)),
             expect_interrupt_in_complete: false,
+            protocol: ProtocolMode::Report,
+            idle_rate: 0,
+            last_report: Vec::new(),
+            since_last_report: 0,
+            queue: Deque::new(),
+            suspended: false,
         }
     }
 
-    pub fn new_with_polling_interval(device: D, alloc: &UsbBusAllocator<B>, interval: u8) -> HidClass<'_, B, D> {
+    pub fn new_with_polling_interval(
+        device: D,
+        alloc: &UsbBusAllocator<B>,
+        interval: u8,
+    ) -> HidClass<'_, B, D> {
         let max_packet_size = device.max_packet_size();
+        let out_max_packet_size = device.out_max_packet_size();
+        let interface_string = device.interface_name().is_some();
         HidClass {
             device,
             interface: alloc.interface(),
+            interface_string: interface_string.then(|| alloc.string()),
             endpoint_interrupt_in: alloc.interrupt(max_packet_size, interval),
+            endpoint_interrupt_out: out_max_packet_size.map(|size| alloc.interrupt(size, interval)),
             expect_interrupt_in_complete: false,
+            protocol: ProtocolMode::Report,
+            idle_rate: 0,
+            last_report: Vec::new(),
+            since_last_report: 0,
+            queue: Deque::new(),
+            suspended: false,
         }
     }
 
@@ -134,8 +254,42 @@ impl<B: UsbBus, D: HidDevice> HidClass<'_, B, D> {
         &mut self.device
     }
 
+    /// Returns the protocol selected by the host.
+    pub fn protocol_mode(&self) -> ProtocolMode {
+        self.protocol
+    }
+
+    /// Returns the idle rate, in units of 4 milliseconds, 0 meaning
+    /// infinite.
+    ///
+    /// The keyberon devices don't use report IDs: only the idle rate
+    /// of all the reports (report ID 0) is supported, the requests
+    /// for a specific report ID being rejected.
+    pub fn idle_rate(&self) -> u8 {
+        self.idle_rate
+    }
+
+    /// Informs the class that the USB bus is suspended (`true`) or
+    /// resumed (`false`).
+    ///
+    /// While suspended, no report is sent or queued: the pending
+    /// reports are discarded, as they would be outdated on resume.
+    /// Typically, a key press should then trigger a remote wakeup
+    /// (see [`Layout::take_wakeup_request`](crate::layout::Layout::take_wakeup_request)).
+    pub fn set_suspended(&mut self, suspended: bool) {
+        self.suspended = suspended;
+        if suspended {
+            self.queue.clear();
+        }
+    }
+
+    /// Returns `true` if the USB bus is suspended.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
     pub fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
-        if self.expect_interrupt_in_complete {
+        if self.suspended || self.expect_interrupt_in_complete {
             return Ok(0);
         }
 
@@ -144,12 +298,70 @@ impl<B: UsbBus, D: HidDevice> HidClass<'_, B, D> {
         }
 
         match self.endpoint_interrupt_in.write(data) {
-            Ok(count) => Ok(count),
+            Ok(count) => {
+                self.last_report.clear();
+                let _ = self.last_report.extend_from_slice(data);
+                self.since_last_report = 0;
+                Ok(count)
+            }
             Err(UsbError::WouldBlock) => Ok(0),
             Err(_) => Err(Error),
         }
     }
 
+    /// Queues a report, sent as soon as the previous reports are
+    /// sent.
+    ///
+    /// Unlike [`write`](HidClass::write), a report is never dropped
+    /// when the host didn't read the previous one yet: every report
+    /// is sent, in order. Typically, a report is pushed at each change
+    /// of the layout state. If the queue is full, the report replaces
+    /// the last queued one: the intermediate states are lost, but the
+    /// final state is always sent. The report is discarded if the USB
+    /// bus is suspended.
+    pub fn push_report(&mut self, data: &[u8]) -> Result<(), Error> {
+        if self.suspended {
+            return Ok(());
+        }
+        let report = Vec::from_slice(data).map_err(|_| Error)?;
+        if let Err(report) = self.queue.push_back(report) {
+            if let Some(last) = self.queue.back_mut() {
+                *last = report;
+            }
+        }
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        while let Some(report) = self.queue.front() {
+            let report = report.clone();
+            if self.write(&report)? == 0 {
+                break;
+            }
+            self.queue.pop_front();
+        }
+        Ok(())
+    }
+
+    /// A time event, managing the idle rate.
+    ///
+    /// This method must be called every millisecond. When the idle
+    /// period of the last sent report expires, the report is sent
+    /// again, as required by the HID specification. See
+    /// [`idle_rate`](HidClass::idle_rate).
+    pub fn tick(&mut self) -> Result<usize, Error> {
+        self.since_last_report = self.since_last_report.saturating_add(1);
+        let rate = self.idle_rate;
+        if rate == 0 || self.last_report.is_empty() || !self.queue.is_empty() {
+            return Ok(0);
+        }
+        if self.since_last_report < rate as u32 * 4 {
+            return Ok(0);
+        }
+        let report = self.last_report.clone();
+        self.write(&report)
+    }
+
     fn get_report(&mut self, xfer: ControlIn<B>) {
         let req = xfer.request();
         let [report_type, report_id] = req.value.to_be_bytes();
@@ -170,43 +382,91 @@ impl<B: UsbBus, D: HidDevice> HidClass<'_, B, D> {
         };
     }
 
-    fn interface_index(&self) -> u16 {
+    fn get_protocol(&mut self, xfer: ControlIn<B>) {
+        xfer.accept_with(&[self.protocol as u8]).ok();
+    }
+
+    fn set_protocol(&mut self, xfer: ControlOut<B>) {
+        let protocol = match xfer.request().value {
+            0 => ProtocolMode::Boot,
+            1 => ProtocolMode::Report,
+            _ => {
+                xfer.reject().ok();
+                return;
+            }
+        };
+        self.protocol = protocol;
+        self.device.set_protocol(protocol);
+        xfer.accept().ok();
+    }
+
+    fn get_idle(&mut self, xfer: ControlIn<B>) {
+        let [_, report_id] = xfer.request().value.to_be_bytes();
+        if report_id == 0 {
+            xfer.accept_with(&[self.idle_rate]).ok();
+        } else {
+            xfer.reject().ok();
+        }
+    }
+
+    fn set_idle(&mut self, xfer: ControlOut<B>) {
+        let [rate, report_id] = xfer.request().value.to_be_bytes();
+        if report_id == 0 {
+            self.idle_rate = rate;
+            xfer.accept().ok();
+        } else {
+            xfer.reject().ok();
+        }
+    }
+
+    pub(crate) fn interface_index(&self) -> u16 {
         let iface: u8 = self.interface.into();
         iface as u16
     }
 }
 
 impl<B: UsbBus, D: HidDevice> UsbClass<B> for HidClass<'_, B, D> {
-    fn poll(&mut self) {}
+    fn poll(&mut self) {
+        let _ = self.flush();
+    }
 
     fn reset(&mut self) {
         self.expect_interrupt_in_complete = false;
+        self.protocol = ProtocolMode::Report;
+        self.device.set_protocol(ProtocolMode::Report);
+        self.idle_rate = 0;
+        self.last_report.clear();
+        self.queue.clear();
+        self.suspended = false;
     }
 
     fn get_configuration_descriptors(
         &self,
         writer: &mut DescriptorWriter,
     ) -> usb_device::Result<()> {
-        writer.interface(
+        writer.interface_alt(
             self.interface,
+            DEFAULT_ALTERNATE_SETTING,
             INTERFACE_CLASS_HID,
             self.device.subclass() as u8,
             self.device.protocol() as u8,
+            self.interface_string,
         )?;
 
         let report_descriptor = self.device.report_descriptor();
         let descriptor_len = report_descriptor.len();
-        if descriptor_len > u16::max_value() as usize {
+        if descriptor_len > u16::MAX as usize {
             return Err(UsbError::InvalidState);
         }
         let descriptor_len = (descriptor_len as u16).to_le_bytes();
         let specification_release = SPECIFICATION_RELEASE.to_le_bytes();
+        let country_code = self.device.country_code() as u8;
         writer.write(
             DescriptorType::Hid as u8,
             &[
                 specification_release[0],     // bcdHID.lower
                 specification_release[1],     // bcdHID.upper
-                0,                            // bCountryCode: 0 = not supported
+                country_code,                 // bCountryCode
                 1,                            // bNumDescriptors
                 DescriptorType::Report as u8, // bDescriptorType
                 descriptor_len[0],            // bDescriptorLength.lower
@@ -215,42 +475,69 @@ impl<B: UsbBus, D: HidDevice> UsbClass<B> for HidClass<'_, B, D> {
         )?;
 
         writer.endpoint(&self.endpoint_interrupt_in)?;
+        if let Some(endpoint) = &self.endpoint_interrupt_out {
+            writer.endpoint(endpoint)?;
+        }
 
         Ok(())
     }
 
-    fn get_string(&self, _index: StringIndex, _lang_id: LangID) -> Option<&str> {
-        None
+    fn get_string(&self, index: StringIndex, _lang_id: LangID) -> Option<&str> {
+        if Some(index) == self.interface_string {
+            self.device.interface_name()
+        } else {
+            None
+        }
     }
 
     fn endpoint_in_complete(&mut self, addr: EndpointAddress) {
         if addr == self.endpoint_interrupt_in.address() {
             self.expect_interrupt_in_complete = false;
+            let _ = self.flush();
         }
     }
 
-    fn endpoint_out(&mut self, _addr: EndpointAddress) {}
+    fn endpoint_out(&mut self, addr: EndpointAddress) {
+        let endpoint = match &self.endpoint_interrupt_out {
+            Some(endpoint) if endpoint.address() == addr => endpoint,
+            _ => return,
+        };
+        let mut buf = [0; 64];
+        let len = match endpoint.read(&mut buf) {
+            Ok(len) => len,
+            Err(_) => return,
+        };
+        if let Some(answer) = self.device.out_report(&buf[..len]) {
+            if let Ok(answer) = Vec::<u8, 64>::from_slice(answer) {
+                let _ = self.push_report(&answer);
+            }
+        }
+    }
 
     fn control_in(&mut self, xfer: ControlIn<B>) {
         let req = xfer.request();
         match (req.request_type, req.recipient) {
-            (RequestType::Standard, Recipient::Interface) => {
-                if req.request == control::Request::GET_DESCRIPTOR {
-                    let (dtype, index) = req.descriptor_type_index();
-                    if dtype == DescriptorType::Report as u8
-                        && index == 0
-                        && req.index == self.interface_index()
-                    {
-                        let descriptor = self.device.report_descriptor();
-                        xfer.accept_with(descriptor).ok();
-                    }
+            (RequestType::Standard, Recipient::Interface)
+                if req.request == control::Request::GET_DESCRIPTOR =>
+            {
+                let (dtype, index) = req.descriptor_type_index();
+                if dtype == DescriptorType::Report as u8
+                    && index == 0
+                    && req.index == self.interface_index()
+                {
+                    let descriptor = self.device.report_descriptor();
+                    xfer.accept_with(descriptor).ok();
                 }
             }
             (RequestType::Class, Recipient::Interface) => {
-                if let Some(request) = Request::new(req.request) {
-                    if request == Request::GetReport && req.index == self.interface_index() {
-                        self.get_report(xfer);
-                    }
+                if req.index != self.interface_index() {
+                    return;
+                }
+                match Request::new(req.request) {
+                    Some(Request::GetReport) => self.get_report(xfer),
+                    Some(Request::GetIdle) => self.get_idle(xfer),
+                    Some(Request::GetProtocol) => self.get_protocol(xfer),
+                    _ => {}
                 }
             }
             _ => {}
@@ -260,11 +547,471 @@ impl<B: UsbBus, D: HidDevice> UsbClass<B> for HidClass<'_, B, D> {
     fn control_out(&mut self, xfer: ControlOut<B>) {
         let req = xfer.request();
         if req.request_type == RequestType::Class && req.recipient == Recipient::Interface {
-            if let Some(request) = Request::new(req.request) {
-                if request == Request::SetReport && req.index == self.interface_index() {
-                    self.set_report(xfer);
+            if req.index != self.interface_index() {
+                return;
+            }
+            match Request::new(req.request) {
+                Some(Request::SetReport) => self.set_report(xfer),
+                Some(Request::SetIdle) => self.set_idle(xfer),
+                Some(Request::SetProtocol) => self.set_protocol(xfer),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    extern crate std;
+    use super::*;
+    use std::sync::Mutex;
+    use usb_device::bus::PollResult;
+    use usb_device::endpoint::EndpointType;
+    use usb_device::prelude::*;
+    use usb_device::UsbDirection;
+
+    /// A USB bus recording the written packets.
+    #[derive(Default)]
+    pub(crate) struct MockBus {
+        next_ep: u8,
+        written: Mutex<std::vec::Vec<(EndpointAddress, std::vec::Vec<u8>)>>,
+        to_read: Mutex<Option<std::vec::Vec<u8>>>,
+        control_data: Mutex<Option<std::vec::Vec<u8>>>,
+        setup: Mutex<Option<[u8; 8]>>,
+        control_in_complete: Mutex<bool>,
+        stalled: Mutex<bool>,
+    }
+
+    impl MockBus {
+        pub(crate) fn take_written(&self) -> std::vec::Vec<(EndpointAddress, std::vec::Vec<u8>)> {
+            core::mem::take(&mut *self.written.lock().unwrap())
+        }
+    }
+
+    /// Returns a setup packet.
+    pub(crate) fn setup(
+        request_type: u8,
+        request: Request,
+        value: u16,
+        index: u16,
+        length: u16,
+    ) -> [u8; 8] {
+        let [v0, v1] = value.to_le_bytes();
+        let [i0, i1] = index.to_le_bytes();
+        let [l0, l1] = length.to_le_bytes();
+        [request_type, request as u8, v0, v1, i0, i1, l0, l1]
+    }
+
+    /// A class IN request on the interface 0.
+    fn class_in(request: Request, value: u16) -> [u8; 8] {
+        setup(0xa1, request, value, 0, 8)
+    }
+
+    /// A class OUT request without data on the interface 0.
+    fn class_out(request: Request, value: u16) -> [u8; 8] {
+        setup(0x21, request, value, 0, 0)
+    }
+
+    /// Sends a control request, returning the data written on the
+    /// control endpoint, or `None` if the request is rejected.
+    pub(crate) fn control(
+        device: &mut UsbDevice<'_, MockBus>,
+        class: &mut dyn UsbClass<MockBus>,
+        setup: [u8; 8],
+    ) -> Option<std::vec::Vec<u8>> {
+        control_with_data(device, class, setup, &[])
+    }
+
+    /// Sends a control request with an OUT data stage, returning the
+    /// data written on the control endpoint, or `None` if the request
+    /// is rejected.
+    pub(crate) fn control_with_data(
+        device: &mut UsbDevice<'_, MockBus>,
+        class: &mut dyn UsbClass<MockBus>,
+        setup: [u8; 8],
+        data: &[u8],
+    ) -> Option<std::vec::Vec<u8>> {
+        *device.bus().setup.lock().unwrap() = Some(setup);
+        *device.bus().control_data.lock().unwrap() = match data {
+            [] => None,
+            data => Some(data.into()),
+        };
+        *device.bus().stalled.lock().unwrap() = false;
+        device.poll(&mut [&mut *class]);
+        while *device.bus().control_in_complete.lock().unwrap()
+            || device.bus().control_data.lock().unwrap().is_some()
+        {
+            device.poll(&mut [&mut *class]);
+        }
+        if *device.bus().stalled.lock().unwrap() {
+            return None;
+        }
+        let written = device.bus().take_written().into_iter();
+        Some(
+            written
+                .filter(|(addr, _)| addr.index() == 0)
+                .flat_map(|(_, data)| data)
+                .collect(),
+        )
+    }
+
+    impl UsbBus for MockBus {
+        fn alloc_ep(
+            &mut self,
+            ep_dir: UsbDirection,
+            ep_addr: Option<EndpointAddress>,
+            _ep_type: EndpointType,
+            _max_packet_size: u16,
+            _interval: u8,
+        ) -> usb_device::Result<EndpointAddress> {
+            if let Some(ep_addr) = ep_addr {
+                return Ok(ep_addr);
+            }
+            self.next_ep += 1;
+            Ok(EndpointAddress::from_parts(self.next_ep as usize, ep_dir))
+        }
+        fn enable(&mut self) {}
+        fn reset(&self) {}
+        fn set_device_address(&self, _addr: u8) {}
+        fn write(&self, ep_addr: EndpointAddress, buf: &[u8]) -> usb_device::Result<usize> {
+            self.written.lock().unwrap().push((ep_addr, buf.into()));
+            if ep_addr.index() == 0 {
+                *self.control_in_complete.lock().unwrap() = true;
+            }
+            Ok(buf.len())
+        }
+        fn read(&self, ep_addr: EndpointAddress, buf: &mut [u8]) -> usb_device::Result<usize> {
+            if ep_addr.index() == 0 {
+                if let Some(setup) = self.setup.lock().unwrap().take() {
+                    buf[..8].copy_from_slice(&setup);
+                    return Ok(8);
                 }
+                let data = self
+                    .control_data
+                    .lock()
+                    .unwrap()
+                    .take()
+                    .ok_or(UsbError::WouldBlock)?;
+                buf[..data.len()].copy_from_slice(&data);
+                return Ok(data.len());
             }
+            let data = self
+                .to_read
+                .lock()
+                .unwrap()
+                .take()
+                .ok_or(UsbError::WouldBlock)?;
+            buf[..data.len()].copy_from_slice(&data);
+            Ok(data.len())
+        }
+        fn set_stalled(&self, _ep_addr: EndpointAddress, stalled: bool) {
+            *self.stalled.lock().unwrap() |= stalled;
+        }
+        fn is_stalled(&self, _ep_addr: EndpointAddress) -> bool {
+            false
+        }
+        fn suspend(&self) {}
+        fn resume(&self) {}
+        fn poll(&self) -> PollResult {
+            let in_complete = core::mem::take(&mut *self.control_in_complete.lock().unwrap());
+            match (*self.setup.lock().unwrap(), in_complete) {
+                (Some(_), _) => PollResult::Data {
+                    ep_out: 0,
+                    ep_in_complete: 0,
+                    ep_setup: 1,
+                },
+                (None, _) if self.control_data.lock().unwrap().is_some() => PollResult::Data {
+                    ep_out: 1,
+                    ep_in_complete: in_complete as u16,
+                    ep_setup: 0,
+                },
+                (None, true) => PollResult::Data {
+                    ep_out: 0,
+                    ep_in_complete: 1,
+                    ep_setup: 0,
+                },
+                (None, false) => PollResult::None,
+            }
+        }
+    }
+
+    struct MockDevice;
+
+    impl HidDevice for MockDevice {
+        fn subclass(&self) -> Subclass {
+            Subclass::None
+        }
+        fn protocol(&self) -> Protocol {
+            Protocol::None
+        }
+        fn max_packet_size(&self) -> u16 {
+            8
+        }
+        fn report_descriptor(&self) -> &[u8] {
+            &[]
+        }
+        fn set_report(&mut self, _: ReportType, _: u8, _: &[u8]) -> Result<(), Error> {
+            Err(Error)
+        }
+        fn get_report(&mut self, _: ReportType, _: u8) -> Result<&[u8], Error> {
+            Err(Error)
+        }
+    }
+
+    #[test]
+    fn report_queue() {
+        let alloc = UsbBusAllocator::new(MockBus::default());
+        let mut class = HidClass::new(MockDevice, &alloc);
+        let addr = class.endpoint_interrupt_in.address();
+        let device = UsbDeviceBuilder::new(&alloc, UsbVidPid(0, 0)).build();
+        let bus = device.bus();
+
+        // the first report is sent immediately, the others are queued
+        for i in 1..=3 {
+            assert!(class.push_report(&[i; 8]).is_ok());
+        }
+        assert_eq!(std::vec![(addr, std::vec![1; 8])], bus.take_written());
+        class.poll();
+        assert!(bus.take_written().is_empty());
+
+        // a report is sent at each transfer completion
+        class.endpoint_in_complete(addr);
+        assert_eq!(std::vec![(addr, std::vec![2; 8])], bus.take_written());
+        class.endpoint_in_complete(addr);
+        assert_eq!(std::vec![(addr, std::vec![3; 8])], bus.take_written());
+        class.endpoint_in_complete(addr);
+        assert!(bus.take_written().is_empty());
+
+        // when the queue is full, the last queued report is replaced
+        for i in 0..=REPORT_QUEUE_SIZE as u8 + 2 {
+            assert!(class.push_report(&[i; 8]).is_ok());
+        }
+        assert_eq!(std::vec![(addr, std::vec![0; 8])], bus.take_written());
+        let mut sent = std::vec![];
+        for _ in 0..=REPORT_QUEUE_SIZE {
+            class.endpoint_in_complete(addr);
+            sent.extend(bus.take_written().into_iter().map(|(_, r)| r[0]));
+        }
+        assert_eq!(std::vec![1, 2, 3, 4, 5, 6, 7, 10], sent);
+    }
+
+    #[test]
+    fn layout_reports() {
+        use crate::action::k;
+        use crate::key_code::KeyCode::*;
+        use crate::layout::{Event, Layers, Layout};
+
+        static LAYERS: Layers<2, 1, 1> = [[[k(A), k(B)]]];
+        let mut layout = Layout::new(&LAYERS);
+        let alloc = UsbBusAllocator::new(MockBus::default());
+        let mut class = HidClass::new(crate::keyboard::Keyboard::new(()), &alloc);
+        let addr = class.endpoint_interrupt_in.address();
+        let device = UsbDeviceBuilder::new(&alloc, UsbVidPid(0, 0)).build();
+        let bus = device.bus();
+        let mut tick = |class: &mut HidClass<'_, _, _>, event| {
+            layout.event(event);
+            layout.tick();
+            assert!(class
+                .push_keyboard_report(layout.keycodes().collect())
+                .is_ok());
+        };
+
+        // only the state changes are sent
+        tick(&mut class, Event::Press(0, 0));
+        tick(&mut class, Event::Press(0, 1));
+        tick(&mut class, Event::Release(0, 0));
+        assert_eq!(
+            std::vec![(addr, std::vec![0, 0, 4, 0, 0, 0, 0, 0])],
+            bus.take_written()
+        );
+        class.endpoint_in_complete(addr);
+        assert_eq!(
+            std::vec![(addr, std::vec![0, 0, 4, 5, 0, 0, 0, 0])],
+            bus.take_written()
+        );
+        class.endpoint_in_complete(addr);
+        assert_eq!(
+            std::vec![(addr, std::vec![0, 0, 5, 0, 0, 0, 0, 0])],
+            bus.take_written()
+        );
+
+        // the host doesn't read the reports, but the final release
+        // is still sent
+        for _ in 0..REPORT_QUEUE_SIZE {
+            tick(&mut class, Event::Press(0, 0));
+            tick(&mut class, Event::Release(0, 0));
+        }
+        tick(&mut class, Event::Release(0, 1));
+        let mut last = std::vec![];
+        for _ in 0..=REPORT_QUEUE_SIZE {
+            class.endpoint_in_complete(addr);
+            last.extend(bus.take_written().into_iter().map(|(_, r)| r));
+        }
+        assert_eq!(Some(&std::vec![0; 8]), last.last());
+    }
+
+    #[test]
+    fn raw_hid() {
+        use crate::raw_hid::{RawHid, RawHidHandler};
+
+        /// Echoes the requests not starting with 0.
+        struct Echo;
+        impl RawHidHandler for Echo {
+            fn handle(&mut self, request: &[u8], response: &mut [u8]) -> bool {
+                response[..request.len()].copy_from_slice(request);
+                request[0] != 0
+            }
+        }
+
+        let alloc = UsbBusAllocator::new(MockBus::default());
+        let mut class = HidClass::new(RawHid::<_, 32>::new(Echo), &alloc);
+        let in_addr = class.endpoint_interrupt_in.address();
+        let out_addr = class.endpoint_interrupt_out.as_ref().unwrap().address();
+        let device = UsbDeviceBuilder::new(&alloc, UsbVidPid(0, 0)).build();
+        let bus = device.bus();
+
+        *bus.to_read.lock().unwrap() = Some(std::vec![1, 2, 3]);
+        class.endpoint_out(out_addr);
+        let mut expected = std::vec![0; 32];
+        expected[..3].copy_from_slice(&[1, 2, 3]);
+        assert_eq!(std::vec![(in_addr, expected)], bus.take_written());
+
+        class.endpoint_in_complete(in_addr);
+        *bus.to_read.lock().unwrap() = Some(std::vec![0, 2, 3]);
+        class.endpoint_out(out_addr);
+        assert!(bus.take_written().is_empty());
+    }
+
+    #[test]
+    fn suspended() {
+        let alloc = UsbBusAllocator::new(MockBus::default());
+        let mut class = HidClass::new(MockDevice, &alloc);
+        let addr = class.endpoint_interrupt_in.address();
+        let device = UsbDeviceBuilder::new(&alloc, UsbVidPid(0, 0)).build();
+        let bus = device.bus();
+
+        assert!(class.push_report(&[1; 8]).is_ok());
+        assert!(class.push_report(&[2; 8]).is_ok());
+        assert_eq!(std::vec![(addr, std::vec![1; 8])], bus.take_written());
+
+        // nothing is sent or queued while suspended
+        class.set_suspended(true);
+        assert!(class.push_report(&[3; 8]).is_ok());
+        class.endpoint_in_complete(addr);
+        assert!(bus.take_written().is_empty());
+
+        class.set_suspended(false);
+        assert!(class.push_report(&[4; 8]).is_ok());
+        assert_eq!(std::vec![(addr, std::vec![4; 8])], bus.take_written());
+    }
+
+    #[test]
+    fn interface_string() {
+        let alloc = UsbBusAllocator::new(MockBus::default());
+        let class = HidClass::new(MockDevice, &alloc);
+        assert!(class.interface_string.is_none());
+
+        let class = HidClass::new(crate::keyboard::Keyboard::new(()), &alloc);
+        let index = class.interface_string.unwrap();
+        assert_eq!(Some("Keyboard"), class.get_string(index, LangID::EN_US));
+    }
+
+    #[test]
+    fn protocol() {
+        let alloc = UsbBusAllocator::new(MockBus::default());
+        let mut class = HidClass::new(crate::keyboard::Keyboard::new(()), &alloc);
+        let mut device = UsbDeviceBuilder::new(&alloc, UsbVidPid(0, 0)).build();
+        let get_protocol = class_in(Request::GetProtocol, 0);
+
+        // the report protocol is used by default
+        assert_eq!(
+            Some(std::vec![1]),
+            control(&mut device, &mut class, get_protocol)
+        );
+
+        // the host selects the boot protocol
+        let set_boot = class_out(Request::SetProtocol, 0);
+        assert_eq!(
+            Some(std::vec![]),
+            control(&mut device, &mut class, set_boot)
+        );
+        assert_eq!(ProtocolMode::Boot, class.protocol_mode());
+        assert_eq!(
+            Some(std::vec![0]),
+            control(&mut device, &mut class, get_protocol)
+        );
+
+        // invalid protocol, or request for another interface
+        let invalid = class_out(Request::SetProtocol, 2);
+        assert_eq!(None, control(&mut device, &mut class, invalid));
+        let other = setup(0x21, Request::SetProtocol, 1, 1, 0);
+        assert_eq!(None, control(&mut device, &mut class, other));
+        assert_eq!(ProtocolMode::Boot, class.protocol_mode());
+
+        // a reset restores the report protocol
+        UsbClass::reset(&mut class);
+        assert_eq!(ProtocolMode::Report, class.protocol_mode());
+    }
+
+    #[test]
+    fn idle() {
+        let alloc = UsbBusAllocator::new(MockBus::default());
+        let mut class = HidClass::new(MockDevice, &alloc);
+        let addr = class.endpoint_interrupt_in.address();
+        let mut device = UsbDeviceBuilder::new(&alloc, UsbVidPid(0, 0)).build();
+        let get_idle = class_in(Request::GetIdle, 0);
+
+        // infinite by default
+        assert_eq!(
+            Some(std::vec![0]),
+            control(&mut device, &mut class, get_idle)
+        );
+        assert_eq!(Some(8), class.write(&[1; 8]).ok());
+        class.endpoint_in_complete(addr);
+        for _ in 0..1000 {
+            assert_eq!(Some(0), class.tick().ok());
+        }
+
+        // 5 * 4 ms
+        let set_idle = class_out(Request::SetIdle, 5 << 8);
+        assert_eq!(
+            Some(std::vec![]),
+            control(&mut device, &mut class, set_idle)
+        );
+        assert_eq!(5, class.idle_rate());
+        assert_eq!(
+            Some(std::vec![5]),
+            control(&mut device, &mut class, get_idle)
+        );
+
+        // the last report is sent again after 20 ms
+        assert_eq!(Some(8), class.write(&[2; 8]).ok());
+        class.endpoint_in_complete(addr);
+        device.bus().take_written();
+        for _ in 0..19 {
+            assert_eq!(Some(0), class.tick().ok());
+        }
+        assert!(device.bus().take_written().is_empty());
+        assert_eq!(Some(8), class.tick().ok());
+        assert_eq!(
+            std::vec![(addr, std::vec![2; 8])],
+            device.bus().take_written()
+        );
+        class.endpoint_in_complete(addr);
+        for _ in 0..19 {
+            assert_eq!(Some(0), class.tick().ok());
         }
+        assert_eq!(Some(8), class.tick().ok());
+
+        // the report IDs are not supported
+        let set_idle_id = class_out(Request::SetIdle, 3 << 8 | 1);
+        assert_eq!(None, control(&mut device, &mut class, set_idle_id));
+        let get_idle_id = class_in(Request::GetIdle, 1);
+        assert_eq!(None, control(&mut device, &mut class, get_idle_id));
+        assert_eq!(5, class.idle_rate());
+
+        // a reset restores the infinite idle rate
+        UsbClass::reset(&mut class);
+        assert_eq!(0, class.idle_rate());
     }
 }
@@ -321,3 +321,93 @@ impl KbHidReport {
         }
     }
 }
+
+/// A N-key rollover keyboard USB HID report.
+///
+/// It can handle any key code from 0x00 to 0xE7 at the same time,
+/// using a bitmap: a byte for the modifiers followed by 28 bytes for
+/// the key codes from 0x00 to 0xDF.
+#[derive(Default, Debug, Clone, Eq, PartialEq)]
+pub struct NkroKbHidReport([u8; 29]);
+
+impl core::iter::FromIterator<KeyCode> for NkroKbHidReport {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = KeyCode>,
+    {
+        let mut res = Self::default();
+        for kc in iter {
+            res.pressed(kc);
+        }
+        res
+    }
+}
+
+impl NkroKbHidReport {
+    /// Returns the byte slice corresponding to the report.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Add the given key code to the report. The key codes greater
+    /// than 0xE7 are ignored.
+    pub fn pressed(&mut self, kc: KeyCode) {
+        match kc {
+            KeyCode::No => (),
+            kc if kc.is_modifier() => self.0[0] |= kc.as_modifier_bit(),
+            kc if kc < KeyCode::LCtrl => {
+                let kc = kc as usize;
+                self.0[1 + kc / 8] |= 1 << (kc % 8);
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns the corresponding 6 key rollover report, used with the
+    /// boot protocol. If more than 6 keys are pressed, the keys are
+    /// set to `ErrorRollOver`.
+    pub fn to_boot_report(&self) -> KbHidReport {
+        let mut res = KbHidReport::default();
+        res.0[0] = self.0[0];
+        let mut slots = res.0[2..].iter_mut();
+        for (i, byte) in self.0[1..].iter().enumerate() {
+            for bit in (0..8).filter(|bit| byte & 1 << bit != 0) {
+                match slots.next() {
+                    Some(slot) => *slot = (i * 8 + bit) as u8,
+                    None => {
+                        res.set_all(KeyCode::ErrorRollOver);
+                        return res;
+                    }
+                }
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use KeyCode::*;
+
+    #[test]
+    fn nkro_report() {
+        let report: NkroKbHidReport = [LShift, A, B, RAlt, Z, F24].iter().copied().collect();
+        let bytes = report.as_bytes();
+        assert_eq!(29, bytes.len());
+        assert_eq!(0b0100_0010, bytes[0]);
+        assert_eq!(0b0011_0000, bytes[1]);
+        assert_eq!(0b0010_0000, bytes[4]);
+        assert_eq!(0b0000_1000, bytes[15]);
+        assert_eq!(
+            &[0x42, 0, 4, 5, 29, 115, 0, 0],
+            report.to_boot_report().as_bytes()
+        );
+
+        let report: NkroKbHidReport = [A, B, C, D, E, F, G, LCtrl].iter().copied().collect();
+        assert_eq!(
+            &[1, 0, 1, 1, 1, 1, 1, 1],
+            report.to_boot_report().as_bytes()
+        );
+    }
+}
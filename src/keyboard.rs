@@ -1,7 +1,9 @@
 //! Keyboard HID device implementation.
 
-use crate::hid::{self, HidDevice, Protocol, ReportType, Subclass};
-use crate::key_code::KbHidReport;
+use crate::hid::{self, CountryCode, HidDevice, Protocol, ReportType, Subclass};
+use crate::key_code::{KbHidReport, NkroKbHidReport};
+use heapless::Vec;
+use usb_device::bus::UsbBus;
 
 /// A trait to manage keyboard LEDs.
 ///
@@ -20,6 +22,16 @@ pub trait Leds {
 }
 impl Leds for () {}
 
+/// Calls the `Leds` callbacks with the LED state of a keyboard output
+/// report.
+pub(crate) fn set_leds(leds: &mut impl Leds, d: u8) {
+    leds.num_lock(d & 1 != 0);
+    leds.caps_lock(d & 1 << 1 != 0);
+    leds.scroll_lock(d & 1 << 2 != 0);
+    leds.compose(d & 1 << 3 != 0);
+    leds.kana(d & 1 << 4 != 0);
+}
+
 #[rustfmt::skip]
 const REPORT_DESCRIPTOR: &[u8] = &[
     0x05, 0x01,        // Usage Page (Generic Desktop Ctrls)
@@ -56,9 +68,48 @@ const REPORT_DESCRIPTOR: &[u8] = &[
     0xC0,              // End Collection
 ];
 
+#[rustfmt::skip]
+const NKRO_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop Ctrls)
+    0x09, 0x06,        // Usage (Keyboard)
+    0xA1, 0x01,        // Collection (Application)
+    0x05, 0x07,        //   Usage Page (Kbrd/Keypad)
+    0x19, 0xE0,        //   Usage Minimum (0xE0)
+    0x29, 0xE7,        //   Usage Maximum (0xE7)
+    0x15, 0x00,        //   Logical Minimum (0)
+    0x25, 0x01,        //   Logical Maximum (1)
+    0x95, 0x08,        //   Report Count (8)
+    0x75, 0x01,        //   Report Size (1)
+    0x81, 0x02,        //   Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    0x05, 0x07,        //   Usage Page (Kbrd/Keypad)
+    0x19, 0x00,        //   Usage Minimum (0x00)
+    0x29, 0xDF,        //   Usage Maximum (0xDF)
+    0x15, 0x00,        //   Logical Minimum (0)
+    0x25, 0x01,        //   Logical Maximum (1)
+    0x95, 0xE0,        //   Report Count (224)
+    0x75, 0x01,        //   Report Size (1)
+    0x81, 0x02,        //   Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    0x05, 0x08,        //   Usage Page (LEDs)
+    0x19, 0x01,        //   Usage Minimum (Num Lock)
+    0x29, 0x05,        //   Usage Maximum (Kana)
+    0x95, 0x05,        //   Report Count (5)
+    0x75, 0x01,        //   Report Size (1)
+    0x91, 0x02,        //   Output (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position,Non-volatile)
+    0x95, 0x01,        //   Report Count (1)
+    0x75, 0x03,        //   Report Size (3)
+    0x91, 0x03,        //   Output (Const,Var,Abs,No Wrap,Linear,Preferred State,No Null Position,Non-volatile)
+    0xC0,              // End Collection
+];
+
 /// A keyboard HID device.
+///
+/// By default, the keyboard uses the 6 key rollover boot report. A N-key
+/// rollover keyboard can be created using [`Keyboard::new_nkro`].
 pub struct Keyboard<L> {
     report: KbHidReport,
+    nkro_report: Option<NkroKbHidReport>,
+    country_code: CountryCode,
+    leds_report: u8,
     leds: L,
 }
 
@@ -67,9 +118,28 @@ impl<L> Keyboard<L> {
     pub fn new(leds: L) -> Keyboard<L> {
         Keyboard {
             report: KbHidReport::default(),
+            nkro_report: None,
+            country_code: CountryCode::NotSupported,
+            leds_report: 0,
             leds,
         }
     }
+    /// Creates a new N-key rollover `Keyboard` object.
+    ///
+    /// The report must be set using
+    /// [`set_nkro_report`](Keyboard::set_nkro_report).
+    ///
+    /// Its report doesn't fit in the 8 bytes endpoint of a boot
+    /// keyboard, thus it is declared as a non boot interface, ignored
+    /// by the BIOS. To fall back to the 6 key rollover boot report,
+    /// it must be used alongside a 6 key rollover keyboard, as done
+    /// by [`CompositeClass::new_nkro`](crate::CompositeClass::new_nkro).
+    pub fn new_nkro(leds: L) -> Keyboard<L> {
+        Keyboard {
+            nkro_report: Some(NkroKbHidReport::default()),
+            ..Keyboard::new(leds)
+        }
+    }
     /// Set the current keyboard HID report.  Returns `true` if it is modified.
     pub fn set_keyboard_report(&mut self, report: KbHidReport) -> bool {
         if report == self.report {
@@ -79,6 +149,45 @@ impl<L> Keyboard<L> {
             true
         }
     }
+    /// Set the current N-key rollover keyboard HID report. Returns
+    /// `true` if it is modified.
+    ///
+    /// The report to send is then given by
+    /// [`report_bytes`](Keyboard::report_bytes).
+    pub fn set_nkro_report(&mut self, report: NkroKbHidReport) -> bool {
+        let modified = self.set_keyboard_report(report.to_boot_report());
+        match &mut self.nkro_report {
+            Some(r) if *r != report => {
+                *r = report;
+                true
+            }
+            _ => modified,
+        }
+    }
+    /// Returns the bytes of the current report: the N-key rollover
+    /// report for a keyboard created with
+    /// [`new_nkro`](Keyboard::new_nkro), the 6 key rollover report
+    /// otherwise.
+    pub fn report_bytes(&self) -> &[u8] {
+        match &self.nkro_report {
+            Some(report) => report.as_bytes(),
+            None => self.report.as_bytes(),
+        }
+    }
+
+    /// Sets the country code of the HID descriptor, declaring the
+    /// localization of the keyboard. It must be set before the
+    /// enumeration of the device.
+    pub fn set_country_code(&mut self, country_code: CountryCode) {
+        self.country_code = country_code;
+    }
+
+    /// Returns the LED state of the last output report sent by the
+    /// host: num lock is bit 0, caps lock bit 1, scroll lock bit 2,
+    /// compose bit 3 and kana bit 4.
+    pub fn leds_report(&self) -> u8 {
+        self.leds_report
+    }
 
     /// Returns the underlying leds object.
     pub fn leds_mut(&mut self) -> &mut L {
@@ -86,30 +195,58 @@ impl<L> Keyboard<L> {
     }
 }
 
+impl<L: Leds> Keyboard<L> {
+    /// Sets the LED state, as if sent by the host.
+    pub(crate) fn set_leds_report(&mut self, d: u8) {
+        self.leds_report = d;
+        set_leds(&mut self.leds, d);
+    }
+}
+
 impl<L: Leds> HidDevice for Keyboard<L> {
     fn subclass(&self) -> Subclass {
-        Subclass::BootInterface
+        match self.nkro_report {
+            Some(_) => Subclass::None,
+            None => Subclass::BootInterface,
+        }
     }
 
     fn protocol(&self) -> Protocol {
-        Protocol::Keyboard
+        match self.nkro_report {
+            Some(_) => Protocol::None,
+            None => Protocol::Keyboard,
+        }
     }
 
     fn max_packet_size(&self) -> u16 {
-        8
+        match self.nkro_report {
+            Some(_) => 32,
+            None => 8,
+        }
     }
 
     fn report_descriptor(&self) -> &[u8] {
-        REPORT_DESCRIPTOR
+        match self.nkro_report {
+            Some(_) => NKRO_REPORT_DESCRIPTOR,
+            None => REPORT_DESCRIPTOR,
+        }
     }
 
     fn get_report(&mut self, report_type: ReportType, _report_id: u8) -> Result<&[u8], hid::Error> {
         match report_type {
-            ReportType::Input => Ok(self.report.as_bytes()),
+            ReportType::Input => Ok(self.report_bytes()),
             _ => Err(hid::Error),
         }
     }
 
+    fn interface_name(&self) -> Option<&str> {
+        Some("Keyboard")
+    }
+
+    fn country_code(&self) -> CountryCode {
+        self.country_code
+    }
+
     fn set_report(
         &mut self,
         report_type: ReportType,
@@ -117,14 +254,83 @@ impl<L: Leds> HidDevice for Keyboard<L> {
         data: &[u8],
     ) -> Result<(), hid::Error> {
         if report_type == ReportType::Output && report_id == 0 && data.len() == 1 {
-            let d = data[0];
-            self.leds.num_lock(d & 1 != 0);
-            self.leds.caps_lock(d & 1 << 1 != 0);
-            self.leds.scroll_lock(d & 1 << 2 != 0);
-            self.leds.compose(d & 1 << 3 != 0);
-            self.leds.kana(d & 1 << 4 != 0);
+            self.set_leds_report(data[0]);
             return Ok(());
         }
         Err(hid::Error)
     }
 }
+
+impl<B: UsbBus, L: Leds> hid::HidClass<'_, B, Keyboard<L>> {
+    /// Sets the current keyboard HID report and, if it is modified,
+    /// queues it using [`push_report`](hid::HidClass::push_report).
+    ///
+    /// Called after each tick of the layout, every state change of
+    /// the layout is sent to the host:
+    ///
+    /// ```
+    /// use keyberon::layout::Layout;
+    /// use usb_device::bus::UsbBus;
+    ///
+    /// fn tick<B: UsbBus>(layout: &mut Layout<12, 4, 2>, class: &mut keyberon::Class<'_, B, ()>) {
+    ///     layout.tick();
+    ///     if class.push_keyboard_report(layout.keycodes().collect()).is_err() {
+    ///         // the report can't be sent
+    ///     }
+    /// }
+    /// ```
+    pub fn push_keyboard_report(&mut self, report: KbHidReport) -> Result<(), hid::Error> {
+        if self.device_mut().set_keyboard_report(report) {
+            self.push_device_report()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Sets the current N-key rollover keyboard HID report and, if it
+    /// is modified, queues it using
+    /// [`push_report`](hid::HidClass::push_report).
+    pub fn push_nkro_report(&mut self, report: NkroKbHidReport) -> Result<(), hid::Error> {
+        if self.device_mut().set_nkro_report(report) {
+            self.push_device_report()
+        } else {
+            Ok(())
+        }
+    }
+
+    fn push_device_report(&mut self) -> Result<(), hid::Error> {
+        let report: Vec<u8, 64> =
+            Vec::from_slice(self.device_mut().report_bytes()).map_err(|_| hid::Error)?;
+        self.push_report(&report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::key_code::KeyCode::*;
+
+    #[test]
+    fn nkro() {
+        let mut keyboard = Keyboard::new_nkro(());
+        let report: NkroKbHidReport = [LShift, A, B, C, D, E, F, G].iter().copied().collect();
+        assert!(keyboard.set_nkro_report(report.clone()));
+        assert!(!keyboard.set_nkro_report(report.clone()));
+        assert_eq!(report.as_bytes(), keyboard.report_bytes());
+        assert_eq!(
+            Some(report.as_bytes()),
+            keyboard.get_report(ReportType::Input, 0).ok()
+        );
+
+        // the report doesn't fit in a boot keyboard endpoint
+        assert_eq!(Subclass::None, keyboard.subclass());
+        assert_eq!(Protocol::None, keyboard.protocol());
+        assert_eq!(32, keyboard.max_packet_size());
+
+        // the 6 key rollover report is a boot keyboard report
+        let keyboard = Keyboard::new(());
+        assert_eq!(Subclass::BootInterface, keyboard.subclass());
+        assert_eq!(Protocol::Keyboard, keyboard.protocol());
+        assert_eq!(8, keyboard.max_packet_size());
+    }
+}
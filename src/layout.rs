@@ -15,6 +15,7 @@
 /// - [`Action::Trans`]: Lowercase `t`
 /// - [`Action::Layer`]: A number in parentheses: `(1)`, `(4 - 2)`, `(0x4u8 as usize)`
 /// - [`Action::MultipleActions`]: Actions in brackets: `[LCtrl S]`, `[LAlt LCtrl C]`, `[(2) B {Action::NoOp}]`
+/// - [`Action::Sequence`]: String literals are typed on key press, using the US QWERTY layout: `"Hello!\n"`
 /// - Other `Action`s: anything in braces (`{}`) is copied unchanged to the final layout - `{ Action::Custom(42) }`
 ///   simply becomes `Action::Custom(42)`
 ///
@@ -46,8 +47,14 @@
 /// ```
 pub use keyberon_macros::*;
 
-use crate::action::{Action, HoldTapAction, HoldTapConfig};
+use crate::action::{
+    Action, CapsWordConfig, HoldTapAction, HoldTapConfig, LeaderAction, OneShotAction,
+    SequenceEvent, TapDanceAction,
+};
+use crate::consumer::ConsumerUsage;
 use crate::key_code::KeyCode;
+use crate::mouse::{self, MouseConfig, MouseReport};
+use crate::system::SystemUsage;
 use arraydeque::ArrayDeque;
 use heapless::Vec;
 
@@ -68,6 +75,10 @@ pub type Layers<
     K = KeyCode,
 > = [[[Action<T, K>; C]; R]; L];
 
+/// The maximum number of sequences playing or waiting to be played
+/// (see [`Action::Sequence`]).
+pub const SEQUENCE_QUEUE_SIZE: usize = 4;
+
 /// The current event stack.
 ///
 /// Events can be retrieved by iterating over this struct and calling [Stacked::event].
@@ -87,10 +98,70 @@ pub struct Layout<
 {
     layers: &'static [[[Action<T, K>; C]; R]; L],
     default_layer: usize,
+    conditional_layers: &'static [ConditionalLayer],
+    trans_resolution: TransResolution,
     states: Vec<State<T, K>, 64>,
     waiting: Option<WaitingState<T, K>>,
+    tap_dance: Option<TapDanceState<T, K>>,
+    leader: Option<LeaderState<T, K>>,
     stacked: Stack,
     tap_hold_tracker: TapHoldTracker,
+    oneshots: Vec<OneShotState, 8>,
+    sequences: ArrayDeque<SequenceState<K>, SEQUENCE_QUEUE_SIZE, arraydeque::behavior::Saturating>,
+    caps_word: Option<CapsWordState<K>>,
+    is_modifier: fn(&K) -> bool,
+    mouse_config: MouseConfig,
+    mouse_report: MouseReport,
+    suspended: bool,
+    wakeup_requested: bool,
+}
+
+/// A layer activated when some other layers are active.
+///
+/// # Example
+///
+/// The classic "lower + raise = adjust" pattern:
+///
+/// ```
+/// use keyberon::layout::ConditionalLayer;
+///
+/// const LOWER: usize = 1;
+/// const RAISE: usize = 2;
+/// const ADJUST: usize = 3;
+///
+/// static CONDITIONAL_LAYERS: [ConditionalLayer; 1] = [ConditionalLayer {
+///     if_active: &[LOWER, RAISE],
+///     then: ADJUST,
+/// }];
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ConditionalLayer {
+    /// The layers that must all be active.
+    ///
+    /// Only the layers activated by a key (held, toggled or locked)
+    /// and the default layer are considered, not the layers activated
+    /// by other conditions.
+    pub if_active: &'static [usize],
+    /// The layer activated on top of the others.
+    pub then: usize,
+}
+
+/// How `Action::Trans` is resolved.
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum TransResolution {
+    /// Get the action from the default layer.
+    ///
+    /// This is the default.
+    #[default]
+    DefaultLayer,
+    /// Get the action from the layer below the current one, and so
+    /// on until a non transparent action is found. The layers are
+    /// walked in the order used by [`Layout::current_layer`]: the
+    /// fulfilled conditional layers, then the held, toggled and
+    /// locked layers in reverse activation order, and finally the
+    /// default layer.
+    LayerStack,
 }
 
 /// An event on the key matrix.
@@ -179,9 +250,40 @@ impl<T> CustomEvent<T> {
 
 #[derive(Debug, Eq, PartialEq)]
 enum State<T: 'static, K: 'static + Copy> {
-    NormalKey { keycode: K, coord: (u8, u8) },
-    LayerModifier { value: usize, coord: (u8, u8) },
-    Custom { value: &'static T, coord: (u8, u8) },
+    NormalKey {
+        keycode: K,
+        coord: (u8, u8),
+    },
+    LayerModifier {
+        value: usize,
+        coord: (u8, u8),
+    },
+    ToggledLayer {
+        value: usize,
+    },
+    Custom {
+        value: &'static T,
+        coord: (u8, u8),
+    },
+    FakeKey {
+        keycode: K,
+    },
+    MouseKey {
+        key: mouse::MouseKey,
+        coord: (u8, u8),
+        /// Ticks since the press, saturating, for the acceleration.
+        since: u16,
+        /// Ticks before the next movement or scroll.
+        countdown: u16,
+    },
+    ConsumerKey {
+        usage: ConsumerUsage,
+        coord: (u8, u8),
+    },
+    SystemKey {
+        usage: SystemUsage,
+        coord: (u8, u8),
+    },
 }
 impl<T: 'static, K: 'static + Copy> Copy for State<T, K> {}
 impl<T: 'static, K: 'static + Copy> Clone for State<T, K> {
@@ -193,16 +295,37 @@ impl<T: 'static, K: 'static + Copy> Clone for State<T, K> {
 impl<T: 'static, K: 'static + Copy> State<T, K> {
     fn keycode(&self) -> Option<K> {
         match self {
-            NormalKey { keycode, .. } => Some(*keycode),
+            NormalKey { keycode, .. } | FakeKey { keycode } => Some(*keycode),
             _ => None,
         }
     }
     fn tick(&self) -> Option<Self> {
-        Some(*self)
+        Some(match *self {
+            MouseKey {
+                key,
+                coord,
+                since,
+                countdown,
+            } => MouseKey {
+                key,
+                coord,
+                since: since.saturating_add(1),
+                countdown,
+            },
+            _ => *self,
+        })
     }
     fn release(&self, c: (u8, u8), custom: &mut CustomEvent<T>) -> Option<Self> {
         match *self {
-            NormalKey { coord, .. } | LayerModifier { coord, .. } if coord == c => None,
+            NormalKey { coord, .. }
+            | LayerModifier { coord, .. }
+            | MouseKey { coord, .. }
+            | ConsumerKey { coord, .. }
+            | SystemKey { coord, .. }
+                if coord == c =>
+            {
+                None
+            }
             Custom { value, coord } if coord == c => {
                 custom.update(CustomEvent::Release(value));
                 None
@@ -212,12 +335,28 @@ impl<T: 'static, K: 'static + Copy> State<T, K> {
     }
     fn get_layer(&self) -> Option<usize> {
         match self {
-            LayerModifier { value, .. } => Some(*value),
+            LayerModifier { value, .. } | ToggledLayer { value } => Some(*value),
             _ => None,
         }
     }
 }
 
+/// The default modifier predicate: [`KeyCode::is_modifier`] for
+/// [`KeyCode`], and `false` for the other key code types.
+fn is_keycode_modifier<K: 'static>(keycode: &K) -> bool {
+    let keycode: &dyn core::any::Any = keycode;
+    matches!(keycode.downcast_ref::<KeyCode>(), Some(kc) if kc.is_modifier())
+}
+
+fn release_fake_key<T, K: Copy + PartialEq>(states: &mut Vec<State<T, K>, 64>, keycode: K) {
+    if let Some(pos) = states
+        .iter()
+        .position(|s| matches!(s, FakeKey { keycode: k } if *k == keycode))
+    {
+        states.remove(pos);
+    }
+}
+
 #[derive(Debug)]
 struct WaitingState<T: 'static, K: 'static> {
     coord: (u8, u8),
@@ -286,6 +425,113 @@ impl<T, K> WaitingState<T, K> {
     }
 }
 
+#[derive(Debug)]
+struct TapDanceState<T: 'static, K: 'static> {
+    coord: (u8, u8),
+    timeout: u16,
+    count: usize,
+    pressed: bool,
+    config: &'static TapDanceAction<T, K>,
+}
+
+impl<T, K> TapDanceState<T, K> {
+    fn tap(&self) -> &'static Action<T, K> {
+        self.config
+            .taps
+            .get(self.count - 1)
+            .unwrap_or(&Action::NoOp)
+    }
+    fn hold(&self) -> &'static Action<T, K> {
+        self.config
+            .holds
+            .get(self.count - 1)
+            .unwrap_or_else(|| self.tap())
+    }
+    /// Consumes the events of the dancing key, returning the action to
+    /// perform when the dance is over.
+    fn tick(&mut self, stacked: &mut Stack) -> Option<&'static Action<T, K>> {
+        self.timeout = self.timeout.saturating_sub(1);
+        while let Some(s) = stacked.front() {
+            match s.event {
+                Event::Release(i, j) if (i, j) == self.coord => {
+                    stacked.pop_front();
+                    self.pressed = false;
+                    self.timeout = self.config.timeout;
+                    if self.count >= self.config.taps.len() {
+                        return Some(self.tap());
+                    }
+                }
+                Event::Press(i, j) if (i, j) == self.coord => {
+                    stacked.pop_front();
+                    self.pressed = true;
+                    self.timeout = self.config.timeout;
+                    self.count += 1;
+                }
+                // Another key interrupts the dance.
+                _ => return Some(self.tap()),
+            }
+        }
+        if self.timeout > 0 {
+            None
+        } else if self.pressed {
+            Some(self.hold())
+        } else {
+            Some(self.tap())
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LeaderState<T: 'static, K: 'static> {
+    coord: (u8, u8),
+    timeout: u16,
+    keycodes: Vec<K, 8>,
+    config: &'static LeaderAction<T, K>,
+}
+
+impl<T, K: PartialEq> LeaderState<T, K> {
+    /// Returns the action to perform when the capture is over.
+    fn tick(&mut self) -> Option<&'static Action<T, K>> {
+        self.timeout = self.timeout.saturating_sub(1);
+        let keycodes = &self.keycodes[..];
+        let sequences = self.config.sequences;
+        let longer = sequences
+            .iter()
+            .any(|(seq, _)| seq.len() > keycodes.len() && seq.starts_with(keycodes));
+        if longer && self.timeout > 0 {
+            return None;
+        }
+        let action = sequences
+            .iter()
+            .find(|(seq, _)| *seq == keycodes)
+            .map_or(&Action::NoOp, |(_, action)| action);
+        Some(action)
+    }
+}
+
+#[derive(Debug)]
+struct CapsWordState<K: 'static> {
+    config: &'static CapsWordConfig<K>,
+    idle: u16,
+}
+
+#[derive(Debug)]
+struct SequenceState<K: 'static> {
+    remaining: &'static [SequenceEvent<K>],
+    delay: u16,
+    tapped: Option<K>,
+}
+
+impl<K> SequenceState<K> {
+    fn new(events: &'static [SequenceEvent<K>]) -> Self {
+        Self {
+            remaining: events,
+            delay: 0,
+            tapped: None,
+        }
+    }
+}
+
 /// An iterator over the currently stacked events.
 ///
 /// Events can be retrieved by iterating over this struct and calling [Stacked::event].
@@ -335,7 +581,28 @@ impl TapHoldTracker {
     }
 }
 
-impl<const C: usize, const R: usize, const L: usize, T: 'static, K: 'static + Copy>
+/// A one-shot action activated by the key at `coord`.
+///
+/// The states of the action are kept after the release of the key
+/// until another key is pressed (`used`) or the timeout expires.
+#[derive(Debug)]
+struct OneShotState {
+    coord: (u8, u8),
+    timeout: u16,
+    released: bool,
+    used: bool,
+}
+
+impl OneShotState {
+    fn tick(&mut self) {
+        self.timeout = self.timeout.saturating_sub(1);
+    }
+    fn is_done(&self) -> bool {
+        self.released && (self.used || self.timeout == 0)
+    }
+}
+
+impl<const C: usize, const R: usize, const L: usize, T: 'static, K: 'static + Copy + PartialEq>
     Layout<C, R, L, T, K>
 {
     /// Creates a new `Layout` object.
@@ -343,15 +610,97 @@ impl<const C: usize, const R: usize, const L: usize, T: 'static, K: 'static + Co
         Self {
             layers,
             default_layer: 0,
+            conditional_layers: &[],
+            trans_resolution: TransResolution::DefaultLayer,
             states: Vec::new(),
             waiting: None,
+            tap_dance: None,
+            leader: None,
             stacked: ArrayDeque::new(),
             tap_hold_tracker: Default::default(),
+            oneshots: Vec::new(),
+            sequences: ArrayDeque::new(),
+            caps_word: None,
+            is_modifier: is_keycode_modifier::<K>,
+            mouse_config: MouseConfig::default(),
+            mouse_report: MouseReport::default(),
+            suspended: false,
+            wakeup_requested: false,
+        }
+    }
+    /// Creates a new `Layout` object, with layers activated when some
+    /// other layers are active.
+    pub fn new_with_conditional_layers(
+        layers: &'static [[[Action<T, K>; C]; R]; L],
+        conditional_layers: &'static [ConditionalLayer],
+    ) -> Self {
+        Self {
+            conditional_layers,
+            ..Self::new(layers)
         }
     }
     /// Iterates on the key codes of the current state.
+    ///
+    /// When the caps word mode is active, the shift key code is added
+    /// while a shifted key code is held.
     pub fn keycodes(&self) -> impl Iterator<Item = K> + '_ {
-        self.states.iter().filter_map(State::keycode)
+        self.states
+            .iter()
+            .filter_map(State::keycode)
+            .chain(self.caps_word_shift())
+    }
+    fn caps_word_shift(&self) -> Option<K> {
+        let config = self.caps_word.as_ref()?.config;
+        self.states
+            .iter()
+            .filter_map(State::keycode)
+            .any(|kc| config.shifted.contains(&kc))
+            .then_some(config.shift)
+    }
+    /// Iterates on the consumer control usages of the current state.
+    pub fn consumer_usages(&self) -> impl Iterator<Item = ConsumerUsage> + '_ {
+        self.states.iter().filter_map(|s| match s {
+            ConsumerKey { usage, .. } => Some(*usage),
+            _ => None,
+        })
+    }
+    /// Iterates on the system control usages of the current state.
+    pub fn system_usages(&self) -> impl Iterator<Item = SystemUsage> + '_ {
+        self.states.iter().filter_map(|s| match s {
+            SystemKey { usage, .. } => Some(*usage),
+            _ => None,
+        })
+    }
+    /// Returns the mouse report, containing the held buttons and the
+    /// movements since the previous call.
+    pub fn mouse_report(&mut self) -> MouseReport {
+        let mut report = core::mem::take(&mut self.mouse_report);
+        report.buttons = self
+            .states
+            .iter()
+            .filter_map(|s| match s {
+                MouseKey { key, .. } => key.button_bit(),
+                _ => None,
+            })
+            .fold(0, |buttons, bit| buttons | bit);
+        report
+    }
+    /// Sets the predicate telling if a key code is a modifier.
+    ///
+    /// Pressing only modifiers doesn't use the pending one-shot
+    /// actions (see [`OneShotAction`]). By default, it is
+    /// [`KeyCode::is_modifier`] for [`KeyCode`], and no key code is a
+    /// modifier for the other key code types.
+    pub fn set_modifier_predicate(&mut self, is_modifier: fn(&K) -> bool) {
+        self.is_modifier = is_modifier;
+    }
+    /// Sets the configuration of the mouse keys.
+    pub fn set_mouse_config(&mut self, config: MouseConfig) {
+        self.mouse_config = config;
+    }
+    /// Returns `true` if the caps word mode is active.
+    pub fn is_caps_word_active(&self) -> bool {
+        self.caps_word.is_some()
     }
     fn waiting_into_hold(&mut self) -> CustomEvent<T> {
         if let Some(w) = &self.waiting {
@@ -380,6 +729,58 @@ impl<const C: usize, const R: usize, const L: usize, T: 'static, K: 'static + Co
         self.waiting = None;
         CustomEvent::NoEvent
     }
+    fn tap_dance_into_action(&mut self, action: &'static Action<T, K>) -> CustomEvent<T> {
+        if let Some(td) = self.tap_dance.take() {
+            if !td.pressed {
+                // The key has already been released, the action is tapped.
+                let _ = self
+                    .stacked
+                    .push_front(Event::Release(td.coord.0, td.coord.1).into());
+            }
+            self.do_action(action, td.coord, 0)
+        } else {
+            CustomEvent::NoEvent
+        }
+    }
+    fn leader_into_action(&mut self, action: &'static Action<T, K>) -> CustomEvent<T> {
+        if let Some(leader) = self.leader.take() {
+            // The action is tapped.
+            let _ = self
+                .stacked
+                .push_front(Event::Release(leader.coord.0, leader.coord.1).into());
+            self.do_action(action, leader.coord, 0)
+        } else {
+            CustomEvent::NoEvent
+        }
+    }
+    /// Returns `true` if the action is captured by the leader key.
+    fn capture_by_leader(&mut self, action: &'static Action<T, K>) -> bool {
+        use Action::*;
+        let leader = match &mut self.leader {
+            Some(leader) => leader,
+            None => return false,
+        };
+        let keycode = match action {
+            Layer(_) | ToggleLayer(_) | LayerLock => return false,
+            HoldTap(HoldTapAction { tap, .. }) => tap.key_codes().next(),
+            action => action.key_codes().next(),
+        };
+        match keycode {
+            Some(keycode) if leader.keycodes.push(keycode).is_ok() => {
+                let keycodes = &leader.keycodes[..];
+                let sequences = leader.config.sequences;
+                if !sequences.iter().any(|(seq, _)| seq.starts_with(keycodes)) {
+                    // No possible match, the capture is abandoned.
+                    self.leader = None;
+                }
+                true
+            }
+            _ => {
+                self.leader = None;
+                false
+            }
+        }
+    }
     /// A time event.
     ///
     /// This method must be called regularly, typically every millisecond.
@@ -390,62 +791,214 @@ impl<const C: usize, const R: usize, const L: usize, T: 'static, K: 'static + Co
         self.states = self.states.iter().filter_map(State::tick).collect();
         self.stacked.iter_mut().for_each(Stacked::tick);
         self.tap_hold_tracker.tick();
-        match &mut self.waiting {
+        self.tick_sequences();
+        self.tick_caps_word();
+        let mut custom = self.tick_oneshots();
+        custom.update(match &mut self.waiting {
             Some(w) => match w.tick(&self.stacked) {
                 Some(WaitingAction::Hold) => self.waiting_into_hold(),
                 Some(WaitingAction::Tap) => self.waiting_into_tap(),
                 Some(WaitingAction::NoOp) => self.drop_waiting(),
                 None => CustomEvent::NoEvent,
             },
-            None => match self.stacked.pop_front() {
-                Some(s) => self.unstack(s),
-                None => CustomEvent::NoEvent,
+            None => match &mut self.tap_dance {
+                Some(td) => match td.tick(&mut self.stacked) {
+                    Some(action) => self.tap_dance_into_action(action),
+                    None => CustomEvent::NoEvent,
+                },
+                None => match self.leader.as_mut().and_then(LeaderState::tick) {
+                    Some(action) => self.leader_into_action(action),
+                    None => match self.stacked.pop_front() {
+                        Some(s) => self.unstack(s),
+                        None => CustomEvent::NoEvent,
+                    },
+                },
             },
+        });
+        self.tick_mouse();
+        custom
+    }
+    fn tick_sequences(&mut self) {
+        if let Some(seq) = self.sequences.front_mut() {
+            if let Some(keycode) = seq.tapped.take() {
+                release_fake_key(&mut self.states, keycode);
+            } else if seq.delay > 0 {
+                seq.delay -= 1;
+            } else if let Some((event, remaining)) = seq.remaining.split_first() {
+                seq.remaining = remaining;
+                match *event {
+                    SequenceEvent::Press(keycode) => {
+                        let _ = self.states.push(FakeKey { keycode });
+                    }
+                    SequenceEvent::Release(keycode) => {
+                        release_fake_key(&mut self.states, keycode);
+                    }
+                    SequenceEvent::Tap(keycode) => {
+                        let _ = self.states.push(FakeKey { keycode });
+                        seq.tapped = Some(keycode);
+                    }
+                    SequenceEvent::Delay(delay) => seq.delay = delay.saturating_sub(1),
+                }
+            } else {
+                self.states.retain(|s| !matches!(s, FakeKey { .. }));
+                self.sequences.pop_front();
+            }
+        }
+    }
+    fn tick_mouse(&mut self) {
+        use crate::mouse::MouseKey::*;
+        let config = &self.mouse_config;
+        let report = &mut self.mouse_report;
+        for s in self.states.iter_mut() {
+            if let MouseKey {
+                key,
+                since,
+                countdown,
+                ..
+            } = s
+            {
+                let (move_interval, move_delta) = (config.move_interval, config.move_delta(*since));
+                let (interval, delta, value) = match key {
+                    MoveUp => (move_interval, -move_delta, &mut report.y),
+                    MoveDown => (move_interval, move_delta, &mut report.y),
+                    MoveLeft => (move_interval, -move_delta, &mut report.x),
+                    MoveRight => (move_interval, move_delta, &mut report.x),
+                    WheelUp => (config.wheel_interval, 1, &mut report.wheel),
+                    WheelDown => (config.wheel_interval, -1, &mut report.wheel),
+                    WheelLeft => (config.wheel_interval, -1, &mut report.pan),
+                    WheelRight => (config.wheel_interval, 1, &mut report.pan),
+                    Btn1 | Btn2 | Btn3 | Btn4 | Btn5 => continue,
+                };
+                if *countdown > 0 {
+                    *countdown -= 1;
+                    continue;
+                }
+                *countdown = interval.saturating_sub(1);
+                *value = mouse::clamp(value.saturating_add(delta));
+            }
+        }
+    }
+    fn tick_caps_word(&mut self) {
+        if let Some(cw) = &mut self.caps_word {
+            cw.idle = cw.idle.saturating_sub(1);
+            if cw.idle == 0 {
+                self.caps_word = None;
+            }
+        }
+    }
+    fn update_caps_word(&mut self, keycodes: &[K]) {
+        if let Some(cw) = &mut self.caps_word {
+            let config = cw.config;
+            if keycodes
+                .iter()
+                .all(|kc| config.shifted.contains(kc) || config.continue_with.contains(kc))
+            {
+                cw.idle = config.timeout;
+            } else {
+                self.caps_word = None;
+            }
+        }
+    }
+    fn tick_oneshots(&mut self) -> CustomEvent<T> {
+        self.oneshots.iter_mut().for_each(OneShotState::tick);
+        let mut custom = CustomEvent::NoEvent;
+        while let Some(pos) = self.oneshots.iter().position(OneShotState::is_done) {
+            let oneshot = self.oneshots.remove(pos);
+            custom.update(self.release_states(oneshot.coord));
         }
+        custom
+    }
+    fn release_states(&mut self, coord: (u8, u8)) -> CustomEvent<T> {
+        let mut custom = CustomEvent::NoEvent;
+        self.states = self
+            .states
+            .iter()
+            .filter_map(|s| s.release(coord, &mut custom))
+            .collect();
+        custom
     }
     fn unstack(&mut self, stacked: Stacked) -> CustomEvent<T> {
         use Event::*;
         match stacked.event {
             Release(i, j) => {
-                let mut custom = CustomEvent::NoEvent;
-                self.states = self
-                    .states
-                    .iter()
-                    .filter_map(|s| s.release((i, j), &mut custom))
-                    .collect();
-                custom
+                if let Some(oneshot) = self.oneshots.iter_mut().find(|s| s.coord == (i, j)) {
+                    if !oneshot.used && oneshot.timeout > 0 {
+                        // Tapped: keep the action for the next key press.
+                        oneshot.released = true;
+                        return CustomEvent::NoEvent;
+                    }
+                }
+                self.oneshots.retain(|s| s.coord != (i, j));
+                self.release_states((i, j))
             }
             Press(i, j) => {
-                let action = self.press_as_action((i, j), self.current_layer());
-                self.do_action(action, (i, j), stacked.since)
+                let action = self.press_as_action((i, j));
+                if self.capture_by_leader(action) {
+                    return CustomEvent::NoEvent;
+                }
+                // A pending one-shot on the same key must be released
+                // before its states get mixed with the new ones.
+                let mut custom = CustomEvent::NoEvent;
+                if let Some(pos) = self.oneshots.iter().position(|s| s.coord == (i, j)) {
+                    self.oneshots.remove(pos);
+                    custom = self.release_states((i, j));
+                }
+                custom.update(self.do_action(action, (i, j), stacked.since));
+                custom
             }
         }
     }
+    fn use_oneshots(&mut self) {
+        self.oneshots.iter_mut().for_each(|s| s.used = true);
+    }
+    /// Uses the pending one-shot actions, unless only modifiers are
+    /// pressed.
+    fn use_oneshots_with(&mut self, keycodes: &[K]) {
+        if !keycodes.iter().all(self.is_modifier) {
+            self.use_oneshots();
+        }
+    }
     /// Register a key event.
+    ///
+    /// While the USB bus is suspended, a key press is not registered,
+    /// but requests a remote wakeup (see
+    /// [`take_wakeup_request`](Layout::take_wakeup_request)).
     pub fn event(&mut self, event: Event) {
+        if self.suspended && event.is_press() {
+            self.wakeup_requested = true;
+            return;
+        }
         if let Some(stacked) = self.stacked.push_back(event.into()) {
             self.waiting_into_hold();
+            if let Some(td) = &self.tap_dance {
+                self.tap_dance_into_action(td.tap());
+            }
             self.unstack(stacked);
         }
     }
-    fn press_as_action(&self, coord: (u8, u8), layer: usize) -> &'static Action<T, K> {
+    fn press_as_action(&self, coord: (u8, u8)) -> &'static Action<T, K> {
         use crate::action::Action::*;
-        let action = self
-            .layers
-            .get(layer)
-            .and_then(|l| l.get(coord.0 as usize))
-            .and_then(|l| l.get(coord.1 as usize));
-        match action {
-            None => &NoOp,
-            Some(Trans) => {
-                if layer != self.default_layer {
-                    self.press_as_action(coord, self.default_layer)
-                } else {
-                    &NoOp
-                }
+        let depth = match self.trans_resolution {
+            TransResolution::DefaultLayer => 1,
+            TransResolution::LayerStack => usize::MAX,
+        };
+        for layer in self
+            .active_layers()
+            .take(depth)
+            .chain(core::iter::once(self.default_layer))
+        {
+            let action = self
+                .layers
+                .get(layer)
+                .and_then(|l| l.get(coord.0 as usize))
+                .and_then(|l| l.get(coord.1 as usize));
+            match action {
+                None => return &NoOp,
+                Some(Trans) => (),
+                Some(action) => return action,
             }
-            Some(action) => action,
         }
+        &NoOp
     }
     fn do_action(
         &mut self,
@@ -453,10 +1006,24 @@ impl<const C: usize, const R: usize, const L: usize, T: 'static, K: 'static + Co
         coord: (u8, u8),
         delay: u16,
     ) -> CustomEvent<T> {
-        assert!(self.waiting.is_none());
+        assert!(self.waiting.is_none() && self.tap_dance.is_none());
         use Action::*;
         match action {
             NoOp | Trans => (),
+            OneShot(OneShotAction { action, timeout }) => {
+                self.tap_hold_tracker.coord = coord;
+                // The one-shot action must not use the pending one-shots.
+                let pending = core::mem::take(&mut self.oneshots);
+                let custom = self.do_action(action, coord, delay);
+                self.oneshots = pending;
+                let _ = self.oneshots.push(OneShotState {
+                    coord,
+                    timeout: *timeout,
+                    released: false,
+                    used: false,
+                });
+                return custom;
+            }
             HoldTap(HoldTapAction {
                 timeout,
                 hold,
@@ -485,12 +1052,26 @@ impl<const C: usize, const R: usize, const L: usize, T: 'static, K: 'static + Co
                 // Need to set tap_hold_tracker coord AFTER the checks.
                 self.tap_hold_tracker.coord = coord;
             }
+            TapDance(config) => {
+                self.tap_hold_tracker.coord = coord;
+                self.tap_dance = Some(TapDanceState {
+                    coord,
+                    timeout: config.timeout,
+                    count: 1,
+                    pressed: true,
+                    config,
+                });
+            }
             &KeyCode(keycode) => {
                 self.tap_hold_tracker.coord = coord;
+                self.use_oneshots_with(core::slice::from_ref(&keycode));
+                self.update_caps_word(core::slice::from_ref(&keycode));
                 let _ = self.states.push(NormalKey { coord, keycode });
             }
             &MultipleKeyCodes(v) => {
                 self.tap_hold_tracker.coord = coord;
+                self.use_oneshots_with(v);
+                self.update_caps_word(v);
                 for &keycode in *v {
                     let _ = self.states.push(NormalKey { coord, keycode });
                 }
@@ -499,6 +1080,12 @@ impl<const C: usize, const R: usize, const L: usize, T: 'static, K: 'static + Co
                 self.tap_hold_tracker.coord = coord;
                 let mut custom = CustomEvent::NoEvent;
                 for action in *v {
+                    // The dance can't wait for the following taps while
+                    // the other actions are performed.
+                    let action = match action {
+                        TapDance(config) => config.taps.first().unwrap_or(&NoOp),
+                        action => action,
+                    };
                     custom.update(self.do_action(action, coord, delay));
                 }
                 return custom;
@@ -507,12 +1094,69 @@ impl<const C: usize, const R: usize, const L: usize, T: 'static, K: 'static + Co
                 self.tap_hold_tracker.coord = coord;
                 let _ = self.states.push(LayerModifier { value, coord });
             }
+            &ToggleLayer(value) => {
+                self.tap_hold_tracker.coord = coord;
+                self.toggle_layer(value);
+            }
+            LayerLock => {
+                self.tap_hold_tracker.coord = coord;
+                let layer = self.current_layer();
+                if self.is_toggled(layer) || layer != self.default_layer {
+                    self.toggle_layer(layer);
+                }
+            }
             DefaultLayer(value) => {
                 self.tap_hold_tracker.coord = coord;
+                self.use_oneshots();
                 self.set_default_layer(*value);
             }
+            Sequence(events) => {
+                self.tap_hold_tracker.coord = coord;
+                self.use_oneshots();
+                let _ = self.sequences.push_back(SequenceState::new(events));
+            }
+            &Mouse(key) => {
+                self.tap_hold_tracker.coord = coord;
+                self.use_oneshots();
+                let _ = self.states.push(MouseKey {
+                    key,
+                    coord,
+                    since: 0,
+                    countdown: 0,
+                });
+            }
+            &Consumer(usage) => {
+                self.tap_hold_tracker.coord = coord;
+                self.use_oneshots();
+                let _ = self.states.push(ConsumerKey { usage, coord });
+            }
+            &System(usage) => {
+                self.tap_hold_tracker.coord = coord;
+                self.use_oneshots();
+                let _ = self.states.push(SystemKey { usage, coord });
+            }
+            Leader(config) => {
+                self.tap_hold_tracker.coord = coord;
+                self.leader = Some(LeaderState {
+                    coord,
+                    timeout: config.timeout.saturating_sub(delay),
+                    keycodes: Vec::new(),
+                    config,
+                });
+            }
+            CapsWord(config) => {
+                self.tap_hold_tracker.coord = coord;
+                self.caps_word = match self.caps_word {
+                    Some(_) => None,
+                    None => Some(CapsWordState {
+                        config,
+                        idle: config.timeout,
+                    }),
+                };
+            }
             Custom(value) => {
                 self.tap_hold_tracker.coord = coord;
+                self.use_oneshots();
                 if self.states.push(State::Custom { value, coord }).is_ok() {
                     return CustomEvent::Press(value);
                 }
@@ -521,13 +1165,85 @@ impl<const C: usize, const R: usize, const L: usize, T: 'static, K: 'static + Co
         CustomEvent::NoEvent
     }
 
+    fn toggle_layer(&mut self, value: usize) {
+        let toggled = |s: &State<T, K>| matches!(s, ToggledLayer { value: v } if *v == value);
+        if let Some(pos) = self.states.iter().position(toggled) {
+            self.states.remove(pos);
+        } else if value < self.layers.len() {
+            let _ = self.states.push(ToggledLayer { value });
+        }
+    }
+
+    fn is_toggled(&self, layer: usize) -> bool {
+        self.states
+            .iter()
+            .any(|s| matches!(s, ToggledLayer { value } if *value == layer))
+    }
+
     /// Obtain the index of the current active layer
+    ///
+    /// The last activated layer, held, toggled or locked, defines the
+    /// current layer. If no layer is activated, it is the default
+    /// layer. A conditional layer whose condition is fulfilled takes
+    /// precedence over all of them.
     pub fn current_layer(&self) -> usize {
+        self.active_layers().next().unwrap_or(self.default_layer)
+    }
+
+    /// Iterates on the active layers, from the current layer to the
+    /// default layer.
+    fn active_layers(&self) -> impl Iterator<Item = usize> + '_ {
+        self.conditional_layers
+            .iter()
+            .rev()
+            .filter(move |c| c.then < L && c.if_active.iter().all(|&l| self.is_key_layer_active(l)))
+            .map(|c| c.then)
+            .chain(self.key_layers())
+    }
+
+    /// Iterates on the layers activated by keys, from the last
+    /// activated to the default layer.
+    fn key_layers(&self) -> impl Iterator<Item = usize> + '_ {
         self.states
             .iter()
             .rev()
-            .find_map(State::get_layer)
-            .unwrap_or(self.default_layer)
+            .filter_map(State::get_layer)
+            .chain(core::iter::once(self.default_layer))
+    }
+
+    fn is_key_layer_active(&self, layer: usize) -> bool {
+        self.key_layers().any(|l| l == layer)
+    }
+
+    /// Informs the layout that the USB bus is suspended (`true`) or
+    /// resumed (`false`).
+    pub fn set_suspended(&mut self, suspended: bool) {
+        self.suspended = suspended;
+        if !suspended {
+            self.wakeup_requested = false;
+        }
+    }
+
+    /// Returns `true` if a key has been pressed while the USB bus is
+    /// suspended, and clears the request.
+    ///
+    /// The firmware should then signal a remote wakeup to the host,
+    /// if the host enabled it (see
+    /// `usb_device::device::UsbDevice::remote_wakeup_enabled`). The
+    /// signaling itself depends on the USB peripheral, and is
+    /// provided by the HAL of the MCU.
+    pub fn take_wakeup_request(&mut self) -> bool {
+        core::mem::take(&mut self.wakeup_requested)
+    }
+
+    /// Sets how `Action::Trans` is resolved.
+    pub fn set_trans_resolution(&mut self, value: TransResolution) {
+        self.trans_resolution = value;
+    }
+
+    /// Returns the default layer of the layout.
+    pub fn default_layer(&self) -> usize {
+        self.default_layer
     }
 
     /// Sets the default layer for the layout
@@ -818,66 +1534,935 @@ mod test {
     }
 
     #[test]
-    fn multiple_layers() {
-        static LAYERS: Layers<2, 1, 4> = [
-            [[l(1), l(2)]],
-            [[k(A), l(3)]],
-            [[l(0), k(B)]],
-            [[k(C), k(D)]],
-        ];
+    fn oneshot() {
+        static LAYERS: Layers<3, 1, 1> = [[[
+            OneShot(&OneShotAction {
+                action: k(LShift),
+                timeout: 100,
+            }),
+            k(A),
+            k(B),
+        ]]];
         let mut layout = Layout::new(&LAYERS);
-        assert_eq!(CustomEvent::NoEvent, layout.tick());
-        assert_eq!(0, layout.current_layer());
-        assert_keys(&[], layout.keycodes());
 
-        // press L1
+        // tap the one-shot key, the modifier stays pressed
         layout.event(Press(0, 0));
         assert_eq!(CustomEvent::NoEvent, layout.tick());
-        assert_eq!(1, layout.current_layer());
-        assert_keys(&[], layout.keycodes());
-        // press L3 on L1
-        layout.event(Press(0, 1));
-        assert_eq!(CustomEvent::NoEvent, layout.tick());
-        assert_eq!(3, layout.current_layer());
-        assert_keys(&[], layout.keycodes());
-        // release L1, still on l3
+        assert_keys(&[LShift], layout.keycodes());
         layout.event(Release(0, 0));
         assert_eq!(CustomEvent::NoEvent, layout.tick());
-        assert_eq!(3, layout.current_layer());
-        assert_keys(&[], layout.keycodes());
-        // press and release C on L3
-        layout.event(Press(0, 0));
+        assert_keys(&[LShift], layout.keycodes());
+
+        // the next key press is modified, and the modifier is then released
+        layout.event(Press(0, 1));
         assert_eq!(CustomEvent::NoEvent, layout.tick());
-        assert_keys(&[C], layout.keycodes());
-        layout.event(Release(0, 0));
+        assert_keys(&[LShift, A], layout.keycodes());
         assert_eq!(CustomEvent::NoEvent, layout.tick());
-        assert_keys(&[], layout.keycodes());
-        // release L3, back to L0
+        assert_keys(&[A], layout.keycodes());
+        layout.event(Press(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[A, B], layout.keycodes());
         layout.event(Release(0, 1));
+        layout.event(Release(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
         assert_eq!(CustomEvent::NoEvent, layout.tick());
-        assert_eq!(0, layout.current_layer());
         assert_keys(&[], layout.keycodes());
 
-        // back to empty, going to L2
-        layout.event(Press(0, 1));
+        // without any key press, the modifier is released after the timeout
+        layout.event(Press(0, 0));
+        layout.event(Release(0, 0));
+        for _ in 0..100 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+            assert_keys(&[LShift], layout.keycodes());
+        }
         assert_eq!(CustomEvent::NoEvent, layout.tick());
-        assert_eq!(2, layout.current_layer());
         assert_keys(&[], layout.keycodes());
-        // and press the L0 key on L2
+    }
+
+    #[test]
+    fn oneshot_modifiers() {
+        static LAYERS: Layers<3, 1, 1> = [[[
+            OneShot(&OneShotAction {
+                action: k(LShift),
+                timeout: 100,
+            }),
+            k(LCtrl),
+            k(A),
+        ]]];
+        let mut layout = Layout::new(&LAYERS);
+
+        // pressing a modifier keeps the one-shot modifier
+        layout.event(Press(0, 0));
+        layout.event(Release(0, 0));
+        layout.event(Press(0, 1));
+        for _ in 0..3 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+        }
+        assert_keys(&[LShift, LCtrl], layout.keycodes());
+
+        // until the next non modifier key press
+        layout.event(Press(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LShift, LCtrl, A], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LCtrl, A], layout.keycodes());
+
+        // with a custom predicate, every key code uses the one-shot
+        layout.event(Release(0, 1));
+        layout.event(Release(0, 2));
+        layout.set_modifier_predicate(|_| false);
+        layout.event(Press(0, 0));
+        layout.event(Release(0, 0));
+        layout.event(Press(0, 1));
+        for _ in 0..5 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+        }
+        assert_keys(&[LShift, LCtrl], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LCtrl], layout.keycodes());
+    }
+
+    #[test]
+    fn oneshot_held() {
+        static LAYERS: Layers<2, 1, 1> = [[[
+            OneShot(&OneShotAction {
+                action: k(LCtrl),
+                timeout: 100,
+            }),
+            k(A),
+        ]]];
+        let mut layout = Layout::new(&LAYERS);
+
+        // held, the one-shot key is a classic modifier
+        layout.event(Press(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LCtrl], layout.keycodes());
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LCtrl, A], layout.keycodes());
+        layout.event(Release(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LCtrl], layout.keycodes());
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LCtrl, A], layout.keycodes());
+        layout.event(Release(0, 1));
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LCtrl], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+
+        // held more than the timeout, released with the key
+        layout.event(Press(0, 0));
+        for _ in 0..200 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+            assert_keys(&[LCtrl], layout.keycodes());
+        }
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+    }
+
+    #[test]
+    fn oneshot_layer_and_combination() {
+        static LAYERS: Layers<4, 1, 2> = [
+            [[
+                OneShot(&OneShotAction {
+                    action: l(1),
+                    timeout: 100,
+                }),
+                OneShot(&OneShotAction {
+                    action: k(LShift),
+                    timeout: 100,
+                }),
+                k(A),
+                HoldTap(&HoldTapAction {
+                    timeout: 50,
+                    hold: k(LAlt),
+                    tap: k(Space),
+                    config: HoldTapConfig::Default,
+                    tap_hold_interval: 0,
+                }),
+            ]],
+            [[Trans, Trans, k(B), Trans]],
+        ];
+        let mut layout = Layout::new(&LAYERS);
+
+        // one-shot layer and one-shot shift combined
+        layout.event(Press(0, 0));
+        layout.event(Release(0, 0));
+        layout.event(Press(0, 1));
+        layout.event(Release(0, 1));
+        for _ in 0..4 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+        }
+        assert_eq!(1, layout.current_layer());
+        assert_keys(&[LShift], layout.keycodes());
+        layout.event(Press(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LShift, B], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(0, layout.current_layer());
+        assert_keys(&[B], layout.keycodes());
+        layout.event(Release(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+
+        // the one-shot is kept until the HoldTap is resolved
+        layout.event(Press(0, 1));
+        layout.event(Release(0, 1));
+        layout.event(Press(0, 3));
+        for _ in 0..3 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+            assert_keys(&[LShift], layout.keycodes());
+        }
+        layout.event(Release(0, 3));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LShift, Space], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+    }
+
+    #[test]
+    fn tap_dance() {
+        static LAYERS: Layers<2, 1, 1> = [[[
+            TapDance(&TapDanceAction {
+                timeout: 100,
+                taps: &[k(A), k(B), k(C)],
+                holds: &[k(LCtrl), k(LAlt)],
+            }),
+            k(Enter),
+        ]]];
+        let mut layout = Layout::new(&LAYERS);
+
+        // single tap
+        layout.event(Press(0, 0));
+        layout.event(Release(0, 0));
+        for _ in 0..101 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+            assert_keys(&[], layout.keycodes());
+        }
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[A], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+
+        // double tap
+        layout.event(Press(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        layout.event(Press(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        layout.event(Release(0, 0));
+        for _ in 0..100 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+            assert_keys(&[], layout.keycodes());
+        }
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[B], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+
+        // triple tap, the last action, no need to wait
+        for _ in 0..3 {
+            layout.event(Press(0, 0));
+            layout.event(Release(0, 0));
+        }
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[C], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+    }
+
+    #[test]
+    fn tap_dance_hold() {
+        static LAYERS: Layers<2, 1, 1> = [[[
+            TapDance(&TapDanceAction {
+                timeout: 100,
+                taps: &[k(A), k(B), k(C)],
+                holds: &[k(LCtrl), k(LAlt)],
+            }),
+            k(Enter),
+        ]]];
+        let mut layout = Layout::new(&LAYERS);
+
+        // tap and hold
+        layout.event(Press(0, 0));
+        layout.event(Release(0, 0));
+        layout.event(Press(0, 0));
+        for _ in 0..101 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+            assert_keys(&[], layout.keycodes());
+        }
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LAlt], layout.keycodes());
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LAlt, Enter], layout.keycodes());
+        layout.event(Release(0, 0));
+        layout.event(Release(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+
+        // no hold action for 3 taps, the tap action is held
+        for _ in 0..2 {
+            layout.event(Press(0, 0));
+            layout.event(Release(0, 0));
+        }
+        layout.event(Press(0, 0));
+        for _ in 0..101 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+            assert_keys(&[], layout.keycodes());
+        }
+        for _ in 0..100 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+            assert_keys(&[C], layout.keycodes());
+        }
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+    }
+
+    #[test]
+    fn tap_dance_in_multiple_actions() {
+        static LAYERS: Layers<1, 1, 1> = [[[MultipleActions(
+            &[
+                TapDance(&TapDanceAction {
+                    timeout: 100,
+                    taps: &[k(LShift), k(LCtrl)],
+                    holds: &[],
+                }),
+                k(A),
+            ]
+            .as_slice(),
+        )]]];
+        let mut layout = Layout::new(&LAYERS);
+
+        // the first tap action is performed, as a classic key
+        layout.event(Press(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LShift, A], layout.keycodes());
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+        layout.event(Press(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LShift, A], layout.keycodes());
+    }
+
+    #[test]
+    fn tap_dance_interrupted() {
+        static LAYERS: Layers<2, 1, 1> = [[[
+            TapDance(&TapDanceAction {
+                timeout: 100,
+                taps: &[k(A), k(B), k(C)],
+                holds: &[],
+            }),
+            k(Enter),
+        ]]];
+        let mut layout = Layout::new(&LAYERS);
+
+        // another key press ends the dance
+        layout.event(Press(0, 0));
+        layout.event(Release(0, 0));
+        layout.event(Press(0, 0));
+        layout.event(Release(0, 0));
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[B], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[Enter], layout.keycodes());
+        layout.event(Release(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+
+        // still pressed, the tap action is held
+        layout.event(Press(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[A], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[A, Enter], layout.keycodes());
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[Enter], layout.keycodes());
+    }
+
+    #[test]
+    fn sequence() {
+        use SequenceEvent::*;
+        static LAYERS: Layers<2, 1, 1> = [[[
+            Sequence(
+                &[
+                    Press(LShift),
+                    Tap(A),
+                    Release(LShift),
+                    Delay(3),
+                    Tap(B),
+                    Press(C),
+                ]
+                .as_slice(),
+            ),
+            k(Enter),
+        ]]];
+        let mut layout = Layout::new(&LAYERS);
+
+        layout.event(Event::Press(0, 0));
+        layout.event(Event::Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LShift], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LShift, A], layout.keycodes());
+        layout.event(Event::Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[LShift, Enter], layout.keycodes());
+        layout.event(Event::Release(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+        for _ in 0..3 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+            assert_keys(&[], layout.keycodes());
+        }
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[B], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[C], layout.keycodes());
+        // the keys still pressed are released at the end
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+    }
+
+    #[test]
+    fn sequence_queue() {
+        use SequenceEvent::*;
+        static LAYERS: Layers<1, 1, 1> = [[[Sequence(&[Delay(10), Tap(A)].as_slice())]]];
+        let mut layout = Layout::new(&LAYERS);
+
+        // the sequences exceeding the queue size are ignored
+        for _ in 0..SEQUENCE_QUEUE_SIZE + 2 {
+            layout.event(Event::Press(0, 0));
+            layout.event(Event::Release(0, 0));
+        }
+        let mut taps = 0;
+        for _ in 0..100 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+            if layout.keycodes().next().is_some() {
+                taps += 1;
+            }
+        }
+        assert_eq!(SEQUENCE_QUEUE_SIZE, taps);
+    }
+
+    #[test]
+    fn multiple_layers() {
+        static LAYERS: Layers<2, 1, 4> = [
+            [[l(1), l(2)]],
+            [[k(A), l(3)]],
+            [[l(0), k(B)]],
+            [[k(C), k(D)]],
+        ];
+        let mut layout = Layout::new(&LAYERS);
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(0, layout.current_layer());
+        assert_keys(&[], layout.keycodes());
+
+        // press L1
+        layout.event(Press(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(1, layout.current_layer());
+        assert_keys(&[], layout.keycodes());
+        // press L3 on L1
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(3, layout.current_layer());
+        assert_keys(&[], layout.keycodes());
+        // release L1, still on l3
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(3, layout.current_layer());
+        assert_keys(&[], layout.keycodes());
+        // press and release C on L3
+        layout.event(Press(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[C], layout.keycodes());
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+        // release L3, back to L0
+        layout.event(Release(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(0, layout.current_layer());
+        assert_keys(&[], layout.keycodes());
+
+        // back to empty, going to L2
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(2, layout.current_layer());
+        assert_keys(&[], layout.keycodes());
+        // and press the L0 key on L2
+        layout.event(Press(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(0, layout.current_layer());
+        assert_keys(&[], layout.keycodes());
+        // release the L0, back to L2
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(2, layout.current_layer());
+        assert_keys(&[], layout.keycodes());
+        // release the L2, back to L0
+        layout.event(Release(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(0, layout.current_layer());
+        assert_keys(&[], layout.keycodes());
+    }
+
+    #[test]
+    fn toggle_layer() {
+        static LAYERS: Layers<2, 1, 3> = [
+            [[ToggleLayer(1), l(2)]],
+            [[ToggleLayer(1), k(A)]],
+            [[ToggleLayer(1), k(B)]],
+        ];
+        let mut layout = Layout::new(&LAYERS);
+
+        // toggle layer 1 on
+        layout.event(Press(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(1, layout.current_layer());
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(1, layout.current_layer());
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[A], layout.keycodes());
+        layout.event(Release(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+
+        // toggle layer 1 off
         layout.event(Press(0, 0));
         assert_eq!(CustomEvent::NoEvent, layout.tick());
         assert_eq!(0, layout.current_layer());
-        assert_keys(&[], layout.keycodes());
-        // release the L0, back to L2
         layout.event(Release(0, 0));
         assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(0, layout.current_layer());
+
+        // a layer toggled while a layer is held is above it
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(2, layout.current_layer());
+        layout.event(Press(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(1, layout.current_layer());
+        layout.event(Release(0, 1));
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(1, layout.current_layer());
+    }
+
+    #[test]
+    fn layer_lock() {
+        static LAYERS: Layers<2, 1, 2> = [[[l(1), k(A)]], [[Trans, LayerLock]]];
+        let mut layout = Layout::new(&LAYERS);
+
+        // lock layer 1
+        layout.event(Press(0, 0));
+        layout.event(Press(0, 1));
+        layout.event(Release(0, 1));
+        layout.event(Release(0, 0));
+        for _ in 0..4 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+            assert_eq!(1, layout.current_layer());
+        }
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(1, layout.current_layer());
+
+        // unlock layer 1
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(0, layout.current_layer());
+        layout.event(Release(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(0, layout.current_layer());
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[A], layout.keycodes());
+    }
+
+    #[test]
+    fn trans_resolution() {
+        static LAYERS: Layers<5, 1, 4> = [
+            [[l(1), k(A), k(B), k(C), NoOp]],
+            [[Trans, l(2), k(D), Trans, NoOp]],
+            [[Trans, Trans, Trans, Trans, ToggleLayer(3)]],
+            [[Trans, Trans, Trans, Trans, k(E)]],
+        ];
+        let mut layout = Layout::new(&LAYERS);
+        layout.event(Press(0, 0));
+        layout.event(Press(0, 1));
+        for _ in 0..2 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+        }
+        assert_eq!(2, layout.current_layer());
+
+        // on the default layer
+        layout.event(Press(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[B], layout.keycodes());
+        layout.event(Release(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+
+        // walking down the active layers
+        layout.set_trans_resolution(TransResolution::LayerStack);
+        layout.event(Press(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[D], layout.keycodes());
+        layout.event(Release(0, 2));
+        layout.event(Press(0, 3));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[C], layout.keycodes());
+        layout.event(Release(0, 3));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+
+        // the layers are walked in reverse activation order
+        layout.event(Press(0, 4));
+        layout.event(Release(0, 4));
+        for _ in 0..2 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+        }
+        assert_eq!(3, layout.current_layer());
+        layout.event(Press(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[D], layout.keycodes());
+        layout.event(Release(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        layout.event(Release(0, 1));
+        layout.event(Release(0, 0));
+        for _ in 0..2 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+        }
+        assert_eq!(3, layout.current_layer());
+        layout.event(Press(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[B], layout.keycodes());
+        layout.event(Press(0, 4));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[B, E], layout.keycodes());
+    }
+
+    #[test]
+    fn conditional_layers() {
+        static LAYERS: Layers<3, 1, 4> = [
+            [[l(1), l(2), k(A)]],
+            [[Trans, Trans, k(B)]],
+            [[Trans, Trans, k(C)]],
+            [[Trans, Trans, k(D)]],
+        ];
+        static CONDITIONAL_LAYERS: [ConditionalLayer; 1] = [ConditionalLayer {
+            if_active: &[1, 2],
+            then: 3,
+        }];
+        let mut layout = Layout::new_with_conditional_layers(&LAYERS, &CONDITIONAL_LAYERS);
+
+        layout.event(Press(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(1, layout.current_layer());
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(3, layout.current_layer());
+        layout.event(Press(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[D], layout.keycodes());
+        layout.event(Release(0, 2));
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
         assert_eq!(2, layout.current_layer());
-        assert_keys(&[], layout.keycodes());
-        // release the L2, back to L0
         layout.event(Release(0, 1));
         assert_eq!(CustomEvent::NoEvent, layout.tick());
         assert_eq!(0, layout.current_layer());
+    }
+
+    #[test]
+    fn caps_word() {
+        static CAPS_WORD: CapsWordConfig<KeyCode> = CapsWordConfig {
+            timeout: 10,
+            ..CapsWordConfig::DEFAULT
+        };
+        static LAYERS: Layers<4, 1, 1> = [[[CapsWord(&CAPS_WORD), k(A), k(Kb1), k(Space)]]];
+        let mut layout = Layout::new(&LAYERS);
+
+        layout.event(Press(0, 0));
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert!(layout.is_caps_word_active());
+
+        // letters are shifted
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[A, LShift], layout.keycodes());
+        layout.event(Release(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+
+        // digits are not shifted, but continue the word
+        layout.event(Press(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[Kb1], layout.keycodes());
+        layout.event(Release(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert!(layout.is_caps_word_active());
+
+        // space ends the word
+        layout.event(Press(0, 3));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[Space], layout.keycodes());
+        assert!(!layout.is_caps_word_active());
+        layout.event(Release(0, 3));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[A], layout.keycodes());
+        layout.event(Release(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+
+        // the word ends after the timeout
+        layout.event(Press(0, 0));
+        layout.event(Release(0, 0));
+        for _ in 0..10 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+            assert!(layout.is_caps_word_active());
+        }
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert!(!layout.is_caps_word_active());
+    }
+
+    #[test]
+    fn leader() {
+        static LEADER: LeaderAction<u8, KeyCode> = LeaderAction {
+            timeout: 100,
+            sequences: &[(&[A], Action::Custom(42)), (&[A, B], k(C)), (&[B, B], k(D))],
+        };
+        static LAYERS: Layers<3, 1, 1, u8> = [[[Leader(&LEADER), k(A), k(B)]]];
+        let mut layout = Layout::new(&LAYERS);
+
+        // complete sequence: performed immediately
+        layout.event(Press(0, 0));
+        layout.event(Release(0, 0));
+        layout.event(Press(0, 1));
+        layout.event(Release(0, 1));
+        layout.event(Press(0, 2));
+        for _ in 0..5 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+            assert_keys(&[], layout.keycodes());
+        }
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[C], layout.keycodes());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+        layout.event(Release(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+
+        // prefix of a longer sequence: performed after the timeout
+        layout.event(Press(0, 0));
+        layout.event(Release(0, 0));
+        layout.event(Press(0, 1));
+        layout.event(Release(0, 1));
+        for _ in 0..99 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+            assert_keys(&[], layout.keycodes());
+        }
+        assert_eq!(CustomEvent::Press(&42), layout.tick());
+        assert_eq!(CustomEvent::Release(&42), layout.tick());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+
+        // unknown sequence: abandoned
+        layout.event(Press(0, 0));
+        layout.event(Release(0, 0));
+        layout.event(Press(0, 2));
+        layout.event(Release(0, 2));
+        layout.event(Press(0, 1));
+        layout.event(Release(0, 1));
+        for _ in 0..6 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+            assert_keys(&[], layout.keycodes());
+        }
+        // the capture is over
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[A], layout.keycodes());
+    }
+
+    #[test]
+    fn mouse() {
+        use crate::mouse::MouseKey::*;
+        static LAYERS: Layers<3, 1, 1> = [[[Mouse(Btn1), Mouse(MoveRight), Mouse(WheelDown)]]];
+        let mut layout = Layout::new(&LAYERS);
+        layout.set_mouse_config(MouseConfig {
+            move_interval: 10,
+            move_delta_min: 1,
+            move_delta_max: 11,
+            move_accel_time: 100,
+            wheel_interval: 50,
+        });
+
+        layout.event(Press(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        let report = layout.mouse_report();
+        assert_eq!(1, report.buttons);
+        assert!(!report.has_motion());
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(MouseReport::default(), layout.mouse_report());
+
+        // the movement accelerates
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(1, layout.mouse_report().x);
+        for _ in 0..9 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+        }
+        assert_eq!(0, layout.mouse_report().x);
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(2, layout.mouse_report().x);
+        for _ in 0..100 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+        }
+        assert_eq!(
+            3 + 4 + 5 + 6 + 7 + 8 + 9 + 10 + 11 + 11,
+            layout.mouse_report().x
+        );
+        layout.event(Release(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(MouseReport::default(), layout.mouse_report());
+
+        // scroll
+        layout.event(Press(0, 2));
+        for _ in 0..51 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+        }
+        assert_eq!(-2, layout.mouse_report().wheel);
+        layout.event(Release(0, 2));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(MouseReport::default(), layout.mouse_report());
+
+        // the movement continues when the key is held for a long time
+        layout.event(Press(0, 1));
+        for _ in 0..u16::MAX as u32 + 1000 {
+            layout.tick();
+        }
+        // the movements accumulated so far are discarded
+        layout.mouse_report();
+        for _ in 0..100 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+        }
+        assert_eq!(10 * 11, layout.mouse_report().x);
+    }
+
+    #[test]
+    fn mouse_saturation() {
+        use crate::mouse::MouseKey::*;
+        static LAYERS: Layers<2, 1, 1> = [[[Mouse(MoveUp), Mouse(MoveRight)]]];
+        let mut layout = Layout::new(&LAYERS);
+        layout.set_mouse_config(MouseConfig {
+            move_interval: 1,
+            move_delta_min: 100,
+            move_delta_max: 100,
+            move_accel_time: 0,
+            wheel_interval: 50,
+        });
+
+        // the movements are not read: they accumulate up to the
+        // range of the report descriptor
+        layout.event(Press(0, 0));
+        layout.event(Press(0, 1));
+        for _ in 0..10 {
+            assert_eq!(CustomEvent::NoEvent, layout.tick());
+        }
+        let report = layout.mouse_report();
+        assert_eq!(-127, report.y);
+        assert_eq!(127, report.x);
+        assert_eq!([0, 0x7f, 0x81, 0, 0], report.as_bytes());
+    }
+
+    #[test]
+    fn consumer() {
+        use crate::consumer::{ConsumerReport, ConsumerUsage::*};
+        static LAYERS: Layers<2, 1, 1> = [[[Consumer(VolumeIncrement), Consumer(PlayPause)]]];
+        let mut layout = Layout::new(&LAYERS);
+
+        layout.event(Press(0, 0));
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+        let report: ConsumerReport = layout.consumer_usages().collect();
+        assert_eq!(&[0xE9, 0, 0xCD, 0, 0, 0, 0, 0], report.as_bytes());
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        let report: ConsumerReport = layout.consumer_usages().collect();
+        assert_eq!(&[0xCD, 0, 0, 0, 0, 0, 0, 0], report.as_bytes());
+    }
+
+    #[test]
+    fn system() {
+        use crate::system::{SystemReport, SystemUsage::*};
+        static LAYERS: Layers<2, 1, 1> = [[[System(Sleep), k(A)]]];
+        let mut layout = Layout::new(&LAYERS);
+
+        layout.event(Press(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[], layout.keycodes());
+        let report: SystemReport = layout.system_usages().collect();
+        assert_eq!(&[0b010], report.as_bytes());
+        layout.event(Release(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(None, layout.system_usages().next());
+    }
+
+    #[test]
+    fn suspend() {
+        static LAYERS: Layers<2, 1, 1> = [[[k(A), k(B)]]];
+        let mut layout = Layout::new(&LAYERS);
+
+        layout.event(Press(0, 0));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[A], layout.keycodes());
+        layout.set_suspended(true);
+        assert!(!layout.take_wakeup_request());
+
+        // releases are registered, presses request a wakeup
+        layout.event(Release(0, 0));
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
         assert_keys(&[], layout.keycodes());
+        assert!(layout.take_wakeup_request());
+        assert!(!layout.take_wakeup_request());
+
+        layout.set_suspended(false);
+        layout.event(Release(0, 1));
+        layout.event(Press(0, 1));
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_eq!(CustomEvent::NoEvent, layout.tick());
+        assert_keys(&[B], layout.keycodes());
     }
 
     #[test]
@@ -21,16 +21,24 @@ use usb_device::prelude::*;
 
 pub mod action;
 pub mod chording;
+pub mod composite;
+pub mod consumer;
 pub mod debounce;
 pub mod hid;
 pub mod key_code;
 pub mod keyboard;
 pub mod layout;
 pub mod matrix;
+pub mod mouse;
+pub mod raw_hid;
+pub mod split;
+pub mod system;
 
 /// A handly shortcut for the keyberon USB class type.
 pub type Class<'a, B, L> = hid::HidClass<'a, B, keyboard::Keyboard<L>>;
 
+pub use composite::CompositeClass;
+
 /// USB VIP for a generic keyboard from
 /// https://github.com/obdev/v-usb/blob/master/usbdrv/USB-IDs-for-free.txt
 const VID: u16 = 0x16c0;
@@ -48,16 +56,133 @@ where
     hid::HidClass::new(keyboard::Keyboard::new(leds), bus)
 }
 
-/// Constructor for a keyberon USB device.
+/// Constructor for `CompositeClass`.
+pub fn new_composite_class<B, L>(bus: &UsbBusAllocator<B>, leds: L) -> CompositeClass<'_, B, L>
+where
+    B: usb_device::bus::UsbBus,
+    L: keyboard::Leds,
+{
+    composite::CompositeClass::new(keyboard::Keyboard::new(leds), bus)
+}
+
+/// Configuration of a keyberon USB device.
+///
+/// The default configuration uses a generic keyboard VID/PID, the
+/// "RIIR Task Force" manufacturer, the "Keyberon" product and the
+/// crate version as serial number.
+///
+/// # Example
+///
+/// ```
+/// use usb_device::bus::{UsbBus, UsbBusAllocator};
+/// use usb_device::device::UsbDevice;
+///
+/// fn new_device<B: UsbBus>(bus: &UsbBusAllocator<B>) -> UsbDevice<'_, B> {
+///     keyberon::DeviceConfig::new()
+///         .vid_pid(0x1209, 0x0001)
+///         .manufacturer("ACME")
+///         .product("My Keyboard")
+///         .supports_remote_wakeup(true)
+///         .build(bus)
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceConfig<'a> {
+    vid: u16,
+    pid: u16,
+    manufacturer: &'a str,
+    product: &'a str,
+    serial_number: &'a str,
+    max_power: usize,
+    self_powered: bool,
+    supports_remote_wakeup: bool,
+}
+
+impl Default for DeviceConfig<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> DeviceConfig<'a> {
+    /// Creates the default configuration.
+    pub const fn new() -> Self {
+        DeviceConfig {
+            vid: VID,
+            pid: PID,
+            manufacturer: "RIIR Task Force",
+            product: "Keyberon",
+            serial_number: env!("CARGO_PKG_VERSION"),
+            max_power: 100,
+            self_powered: false,
+            supports_remote_wakeup: false,
+        }
+    }
+    /// Sets the vendor ID and the product ID.
+    pub const fn vid_pid(mut self, vid: u16, pid: u16) -> Self {
+        self.vid = vid;
+        self.pid = pid;
+        self
+    }
+    /// Sets the manufacturer name.
+    pub const fn manufacturer(mut self, manufacturer: &'a str) -> Self {
+        self.manufacturer = manufacturer;
+        self
+    }
+    /// Sets the product name.
+    pub const fn product(mut self, product: &'a str) -> Self {
+        self.product = product;
+        self
+    }
+    /// Sets the serial number.
+    pub const fn serial_number(mut self, serial_number: &'a str) -> Self {
+        self.serial_number = serial_number;
+        self
+    }
+    /// Sets the maximum current drawn from the bus, in milliamperes
+    /// (at most 500).
+    pub const fn max_power(mut self, max_power_ma: usize) -> Self {
+        self.max_power = max_power_ma;
+        self
+    }
+    /// Sets if the device is self powered.
+    pub const fn self_powered(mut self, self_powered: bool) -> Self {
+        self.self_powered = self_powered;
+        self
+    }
+    /// Sets if the device supports remote wakeup, i.e. waking up the
+    /// host from suspend.
+    pub const fn supports_remote_wakeup(mut self, supports_remote_wakeup: bool) -> Self {
+        self.supports_remote_wakeup = supports_remote_wakeup;
+        self
+    }
+    /// Creates the USB device.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the maximum power is greater than 500 mA.
+    pub fn build<B>(&self, bus: &'a UsbBusAllocator<B>) -> usb_device::device::UsbDevice<'a, B>
+    where
+        B: usb_device::bus::UsbBus,
+    {
+        UsbDeviceBuilder::new(bus, UsbVidPid(self.vid, self.pid))
+            .strings(&[StringDescriptors::default()
+                .manufacturer(self.manufacturer)
+                .product(self.product)
+                .serial_number(self.serial_number)])
+            .and_then(|builder| builder.max_power(self.max_power))
+            .expect("Failed to configure UsbDeviceBuilder")
+            .self_powered(self.self_powered)
+            .supports_remote_wakeup(self.supports_remote_wakeup)
+            .build()
+    }
+}
+
+/// Constructor for a keyberon USB device, using the default
+/// [`DeviceConfig`].
 pub fn new_device<B>(bus: &UsbBusAllocator<B>) -> usb_device::device::UsbDevice<'_, B>
 where
     B: usb_device::bus::UsbBus,
 {
-    UsbDeviceBuilder::new(bus, UsbVidPid(VID, PID))
-        .strings(&[StringDescriptors::default()
-            .manufacturer("RIIR Task Force")
-            .product("Keyberon")
-            .serial_number(env!("CARGO_PKG_VERSION"))])
-        .expect("Failed to configure UsbDeviceBuilder")
-        .build()
+    DeviceConfig::new().build(bus)
 }
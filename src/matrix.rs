@@ -1,23 +1,63 @@
 //! Hardware pin switch matrix handling.
 
+use core::marker::PhantomData;
 use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal::spi::{Operation, SpiDevice};
+
+/// The level of the pins when a key is pressed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Polarity {
+    /// The inputs are pulled up, and a pressed key reads low. The
+    /// scanned outputs are set low.
+    #[default]
+    ActiveLow,
+    /// The inputs are pulled down, and a pressed key reads high. The
+    /// scanned outputs are set high.
+    ActiveHigh,
+}
+
+impl Polarity {
+    fn is_active<P: InputPin>(self, pin: &mut P) -> Result<bool, P::Error> {
+        match self {
+            Polarity::ActiveLow => pin.is_low(),
+            Polarity::ActiveHigh => pin.is_high(),
+        }
+    }
+
+    fn set_active<P: OutputPin>(self, pin: &mut P, active: bool) -> Result<(), P::Error> {
+        if active == (self == Polarity::ActiveHigh) {
+            pin.set_high()
+        } else {
+            pin.set_low()
+        }
+    }
+}
+
+/// The scan direction of a [`Matrix`] whose diodes go from the
+/// columns to the rows (COL2ROW, cathodes on the rows): the rows are
+/// driven and the columns are read.
+pub struct Col2Row;
+
+/// The scan direction of a [`Matrix`] whose diodes go from the rows
+/// to the columns (ROW2COL, cathodes on the columns): the columns are
+/// driven and the rows are read.
+pub struct Row2Col;
 
 /// Describes the hardware-level matrix of switches.
 ///
 /// Generic parameters are in order: The type of column pins,
-/// the type of row pins, the number of columns and rows.
+/// the type of row pins, the number of columns and rows, and the
+/// scan direction ([`Col2Row`] or [`Row2Col`]).
 /// **NOTE:** In order to be able to put different pin structs
 /// in an array they have to be downgraded (stripped of their
 /// numbers etc.). Most HAL-s have a method of downgrading pins
 /// to a common (erased) struct. (for example see
 /// [stm32f0xx_hal::gpio::PA0::downgrade](https://docs.rs/stm32f0xx-hal/0.17.1/stm32f0xx_hal/gpio/gpioa/struct.PA0.html#method.downgrade))
-pub struct Matrix<C, R, const CS: usize, const RS: usize>
-where
-    C: InputPin,
-    R: OutputPin,
-{
+pub struct Matrix<C, R, const CS: usize, const RS: usize, D = Col2Row> {
     cols: [C; CS],
     rows: [R; RS],
+    polarity: Polarity,
+    direction: PhantomData<D>,
 }
 
 impl<C, R, const CS: usize, const RS: usize> Matrix<C, R, CS, RS>
@@ -34,7 +74,25 @@ where
         C: InputPin<Error = E>,
         R: OutputPin<Error = E>,
     {
-        let mut res = Self { cols, rows };
+        Self::new_with_polarity(cols, rows, Polarity::ActiveLow)
+    }
+
+    /// Creates a new Matrix with the given polarity.
+    ///
+    /// With [`Polarity::ActiveHigh`], assumes columns are pull-down
+    /// inputs, and rows are output pins which are set low when not
+    /// being scanned.
+    pub fn new_with_polarity<E>(cols: [C; CS], rows: [R; RS], polarity: Polarity) -> Result<Self, E>
+    where
+        C: InputPin<Error = E>,
+        R: OutputPin<Error = E>,
+    {
+        let mut res = Self {
+            cols,
+            rows,
+            polarity,
+            direction: PhantomData,
+        };
         res.clear()?;
         Ok(res)
     }
@@ -44,7 +102,7 @@ where
         R: OutputPin<Error = E>,
     {
         for r in self.rows.iter_mut() {
-            r.set_high()?;
+            self.polarity.set_active(r, false)?;
         }
         Ok(())
     }
@@ -52,7 +110,8 @@ where
     ///
     /// Every row pin in order is pulled low, and then each column
     /// pin is tested; if it's low, the key is marked as pressed.
-    /// Scans the pins and checks which keys are pressed (state is "low").
+    /// Scans the pins and checks which keys are pressed (state is
+    /// "low", or "high" with [`Polarity::ActiveHigh`]).
     ///
     /// Delay function allows pause to let input pins settle
     pub fn get_with_delay<F: FnMut(), E>(&mut self, mut delay: F) -> Result<[[bool; CS]; RS], E>
@@ -63,14 +122,14 @@ where
         let mut keys = [[false; CS]; RS];
 
         for (ri, row) in self.rows.iter_mut().enumerate() {
-            row.set_low()?;
+            self.polarity.set_active(row, true)?;
             delay();
             for (ci, col) in self.cols.iter_mut().enumerate() {
-                if col.is_low()? {
+                if self.polarity.is_active(col)? {
                     keys[ri][ci] = true;
                 }
             }
-            row.set_high()?;
+            self.polarity.set_active(row, false)?;
         }
         Ok(keys)
     }
@@ -79,7 +138,8 @@ where
     ///
     /// Every row pin in order is pulled low, and then each column
     /// pin is tested; if it's low, the key is marked as pressed.
-    /// Scans the pins and checks which keys are pressed (state is "low").
+    /// Scans the pins and checks which keys are pressed (state is
+    /// "low", or "high" with [`Polarity::ActiveHigh`]).
     pub fn get<E>(&mut self) -> Result<[[bool; CS]; RS], E>
     where
         C: InputPin<Error = E>,
@@ -89,6 +149,96 @@ where
     }
 }
 
+impl<C, R, const CS: usize, const RS: usize> Matrix<C, R, CS, RS, Row2Col>
+where
+    C: OutputPin,
+    R: InputPin,
+{
+    /// Creates a new Matrix, scanned from the columns to the rows.
+    ///
+    /// Assumes rows are pull-up inputs, and columns are output pins
+    /// which are set high when not being scanned.
+    pub fn new_row2col<E>(cols: [C; CS], rows: [R; RS]) -> Result<Self, E>
+    where
+        C: OutputPin<Error = E>,
+        R: InputPin<Error = E>,
+    {
+        Self::new_row2col_with_polarity(cols, rows, Polarity::ActiveLow)
+    }
+
+    /// Creates a new Matrix, scanned from the columns to the rows,
+    /// with the given polarity.
+    ///
+    /// With [`Polarity::ActiveHigh`], assumes rows are pull-down
+    /// inputs, and columns are output pins which are set low when not
+    /// being scanned.
+    pub fn new_row2col_with_polarity<E>(
+        cols: [C; CS],
+        rows: [R; RS],
+        polarity: Polarity,
+    ) -> Result<Self, E>
+    where
+        C: OutputPin<Error = E>,
+        R: InputPin<Error = E>,
+    {
+        let mut res = Self {
+            cols,
+            rows,
+            polarity,
+            direction: PhantomData,
+        };
+        res.clear()?;
+        Ok(res)
+    }
+    fn clear<E>(&mut self) -> Result<(), E>
+    where
+        C: OutputPin<Error = E>,
+        R: InputPin<Error = E>,
+    {
+        for c in self.cols.iter_mut() {
+            self.polarity.set_active(c, false)?;
+        }
+        Ok(())
+    }
+    /// Scans the matrix and checks which keys are pressed.
+    ///
+    /// Every column pin in order is activated, and then each row pin
+    /// is tested; if it's active, the key is marked as pressed.
+    ///
+    /// Delay function allows pause to let input pins settle
+    pub fn get_with_delay<F: FnMut(), E>(&mut self, mut delay: F) -> Result<[[bool; CS]; RS], E>
+    where
+        C: OutputPin<Error = E>,
+        R: InputPin<Error = E>,
+    {
+        let mut keys = [[false; CS]; RS];
+
+        for (ci, col) in self.cols.iter_mut().enumerate() {
+            self.polarity.set_active(col, true)?;
+            delay();
+            for (ri, row) in self.rows.iter_mut().enumerate() {
+                if self.polarity.is_active(row)? {
+                    keys[ri][ci] = true;
+                }
+            }
+            self.polarity.set_active(col, false)?;
+        }
+        Ok(keys)
+    }
+
+    /// Scans the matrix and checks which keys are pressed.
+    ///
+    /// Every column pin in order is activated, and then each row pin
+    /// is tested; if it's active, the key is marked as pressed.
+    pub fn get<E>(&mut self) -> Result<[[bool; CS]; RS], E>
+    where
+        C: OutputPin<Error = E>,
+        R: InputPin<Error = E>,
+    {
+        self.get_with_delay(|| ())
+    }
+}
+
 /// Matrix-representation of switches directly attached to the pins ("diodeless").
 ///
 /// Generic parameters are in order: The type of column pins,
@@ -98,6 +248,7 @@ where
     P: InputPin,
 {
     pins: [[Option<P>; CS]; RS],
+    polarity: Polarity,
 }
 
 impl<P, const CS: usize, const RS: usize> DirectPinMatrix<P, CS, RS>
@@ -112,11 +263,23 @@ where
     where
         P: InputPin<Error = E>,
     {
-        let res = Self { pins };
+        Self::new_with_polarity(pins, Polarity::ActiveLow)
+    }
+
+    /// Creates a new DirectPinMatrix with the given polarity.
+    ///
+    /// With [`Polarity::ActiveHigh`], assumes pins are pull-down
+    /// inputs.
+    pub fn new_with_polarity<E>(pins: [[Option<P>; CS]; RS], polarity: Polarity) -> Result<Self, E>
+    where
+        P: InputPin<Error = E>,
+    {
+        let res = Self { pins, polarity };
         Ok(res)
     }
 
-    /// Scans the pins and checks which keys are pressed (state is "low").
+    /// Scans the pins and checks which keys are pressed (state is
+    /// "low", or "high" with [`Polarity::ActiveHigh`]).
     pub fn get<E>(&mut self) -> Result<[[bool; CS]; RS], E>
     where
         P: InputPin<Error = E>,
@@ -126,7 +289,7 @@ where
         for (ri, row) in self.pins.iter_mut().enumerate() {
             for (ci, col_option) in row.iter_mut().enumerate() {
                 if let Some(col) = col_option {
-                    if col.is_low()? {
+                    if self.polarity.is_active(col)? {
                         keys[ri][ci] = true;
                     }
                 }
@@ -135,3 +298,547 @@ where
         Ok(keys)
     }
 }
+
+/// Matrix-representation of switches whose columns are read
+/// through parallel-in/serial-out shift registers (74HC165), the rows
+/// being driven by output pins.
+///
+/// The registers are wired in cascade, the data pin reading the
+/// serial output of the last register. The bits are read from the
+/// input H to the input A of the last register, then of the previous
+/// one, and so on: the first column is wired to the input H of the
+/// last register. Any number of columns can be read this way.
+///
+/// Generic parameters are in order: The type of the clock (CLK),
+/// latch (SH/LD) and data (QH) pins, the type of row pins, the number
+/// of columns and rows.
+pub struct ShiftRegisterMatrix<CLK, LATCH, DATA, R, const CS: usize, const RS: usize> {
+    clk: CLK,
+    latch: LATCH,
+    data: DATA,
+    rows: [R; RS],
+    polarity: Polarity,
+}
+
+impl<CLK, LATCH, DATA, R, const CS: usize, const RS: usize>
+    ShiftRegisterMatrix<CLK, LATCH, DATA, R, CS, RS>
+where
+    CLK: OutputPin,
+    LATCH: OutputPin,
+    DATA: InputPin,
+    R: OutputPin,
+{
+    /// Creates a new ShiftRegisterMatrix.
+    ///
+    /// Assumes the parallel inputs of the registers are pulled up,
+    /// and rows are output pins which are set high when not being
+    /// scanned.
+    pub fn new<E>(clk: CLK, latch: LATCH, data: DATA, rows: [R; RS]) -> Result<Self, E>
+    where
+        CLK: OutputPin<Error = E>,
+        LATCH: OutputPin<Error = E>,
+        DATA: InputPin<Error = E>,
+        R: OutputPin<Error = E>,
+    {
+        Self::new_with_polarity(clk, latch, data, rows, Polarity::ActiveLow)
+    }
+
+    /// Creates a new ShiftRegisterMatrix with the given polarity.
+    ///
+    /// With [`Polarity::ActiveHigh`], assumes the parallel inputs of
+    /// the registers are pulled down, and rows are output pins which
+    /// are set low when not being scanned.
+    pub fn new_with_polarity<E>(
+        clk: CLK,
+        latch: LATCH,
+        data: DATA,
+        rows: [R; RS],
+        polarity: Polarity,
+    ) -> Result<Self, E>
+    where
+        CLK: OutputPin<Error = E>,
+        LATCH: OutputPin<Error = E>,
+        DATA: InputPin<Error = E>,
+        R: OutputPin<Error = E>,
+    {
+        let mut res = Self {
+            clk,
+            latch,
+            data,
+            rows,
+            polarity,
+        };
+        res.clk.set_low()?;
+        res.latch.set_high()?;
+        for r in res.rows.iter_mut() {
+            res.polarity.set_active(r, false)?;
+        }
+        Ok(res)
+    }
+
+    /// Scans the matrix and checks which keys are pressed.
+    ///
+    /// Every row pin in order is activated, the column states are
+    /// loaded in the registers by pulsing the latch pin low, and then
+    /// shifted out on the data pin, one column per clock pulse.
+    ///
+    /// Delay function allows pause to let input pins settle
+    pub fn get_with_delay<F: FnMut(), E>(&mut self, mut delay: F) -> Result<[[bool; CS]; RS], E>
+    where
+        CLK: OutputPin<Error = E>,
+        LATCH: OutputPin<Error = E>,
+        DATA: InputPin<Error = E>,
+        R: OutputPin<Error = E>,
+    {
+        let mut keys = [[false; CS]; RS];
+
+        for (ri, row) in self.rows.iter_mut().enumerate() {
+            self.polarity.set_active(row, true)?;
+            delay();
+            self.latch.set_low()?;
+            self.latch.set_high()?;
+            for key in keys[ri].iter_mut() {
+                *key = self.polarity.is_active(&mut self.data)?;
+                self.clk.set_high()?;
+                self.clk.set_low()?;
+            }
+            self.polarity.set_active(row, false)?;
+        }
+        Ok(keys)
+    }
+
+    /// Scans the matrix and checks which keys are pressed.
+    ///
+    /// Every row pin in order is activated, and then the columns are
+    /// read through the shift registers.
+    pub fn get<E>(&mut self) -> Result<[[bool; CS]; RS], E>
+    where
+        CLK: OutputPin<Error = E>,
+        LATCH: OutputPin<Error = E>,
+        DATA: InputPin<Error = E>,
+        R: OutputPin<Error = E>,
+    {
+        self.get_with_delay(|| ())
+    }
+}
+
+/// An error of a [`SpiShiftRegisterMatrix`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpiMatrixError<S, P> {
+    /// An error of the SPI bus.
+    Spi(S),
+    /// An error of a row pin.
+    Pin(P),
+}
+
+/// Matrix-representation of switches whose columns are driven
+/// through serial-in/parallel-out shift registers (74HC595) on a SPI
+/// bus, the rows being read by input pins.
+///
+/// The registers are wired in cascade, the chip select pin of the
+/// SPI device driving the latch (RCLK) of the registers. The first
+/// column is the output QA of the first register of the cascade, the
+/// ninth column the output QA of the second register, and so on, up
+/// to 64 columns.
+///
+/// Generic parameters are in order: The type of the SPI device, the
+/// type of row pins, the number of columns and rows.
+pub struct SpiShiftRegisterMatrix<SPI, R, const CS: usize, const RS: usize> {
+    spi: SPI,
+    rows: [R; RS],
+    polarity: Polarity,
+}
+
+impl<SPI, R, const CS: usize, const RS: usize> SpiShiftRegisterMatrix<SPI, R, CS, RS>
+where
+    SPI: SpiDevice,
+    R: InputPin,
+{
+    /// Creates a new SpiShiftRegisterMatrix.
+    ///
+    /// Assumes rows are pull-up inputs. The columns are set high when
+    /// not being scanned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than 64 columns.
+    pub fn new<S, P>(spi: SPI, rows: [R; RS]) -> Result<Self, SpiMatrixError<S, P>>
+    where
+        SPI: SpiDevice<Error = S>,
+        R: InputPin<Error = P>,
+    {
+        Self::new_with_polarity(spi, rows, Polarity::ActiveLow)
+    }
+
+    /// Creates a new SpiShiftRegisterMatrix with the given polarity.
+    ///
+    /// With [`Polarity::ActiveHigh`], assumes rows are pull-down
+    /// inputs. The columns are set low when not being scanned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than 64 columns.
+    pub fn new_with_polarity<S, P>(
+        spi: SPI,
+        rows: [R; RS],
+        polarity: Polarity,
+    ) -> Result<Self, SpiMatrixError<S, P>>
+    where
+        SPI: SpiDevice<Error = S>,
+        R: InputPin<Error = P>,
+    {
+        assert!(CS <= 64, "at most 64 columns are supported");
+        let mut res = Self {
+            spi,
+            rows,
+            polarity,
+        };
+        res.set_active_col(None)?;
+        Ok(res)
+    }
+
+    fn set_active_col<S, P>(&mut self, col: Option<usize>) -> Result<(), SpiMatrixError<S, P>>
+    where
+        SPI: SpiDevice<Error = S>,
+        R: InputPin<Error = P>,
+    {
+        let mut registers = 0u64;
+        for ci in 0..CS {
+            if (Some(ci) == col) == (self.polarity == Polarity::ActiveHigh) {
+                registers |= 1 << ci;
+            }
+        }
+        let nb = CS.div_ceil(8);
+        let mut buf = [0; 8];
+        // the last register of the cascade is sent first
+        for (i, byte) in buf[..nb].iter_mut().rev().enumerate() {
+            *byte = (registers >> (8 * i)) as u8;
+        }
+        self.spi
+            .transaction(&mut [Operation::Write(&buf[..nb])])
+            .map_err(SpiMatrixError::Spi)
+    }
+
+    /// Scans the matrix and checks which keys are pressed.
+    ///
+    /// Every column in order is activated through the shift
+    /// registers, and then each row pin is tested; if it's active,
+    /// the key is marked as pressed.
+    ///
+    /// Delay function allows pause to let input pins settle
+    pub fn get_with_delay<F: FnMut(), S, P>(
+        &mut self,
+        mut delay: F,
+    ) -> Result<[[bool; CS]; RS], SpiMatrixError<S, P>>
+    where
+        SPI: SpiDevice<Error = S>,
+        R: InputPin<Error = P>,
+    {
+        let mut keys = [[false; CS]; RS];
+
+        for ci in 0..CS {
+            self.set_active_col(Some(ci))?;
+            delay();
+            for (row, row_keys) in self.rows.iter_mut().zip(keys.iter_mut()) {
+                row_keys[ci] = self.polarity.is_active(row).map_err(SpiMatrixError::Pin)?;
+            }
+        }
+        self.set_active_col(None)?;
+        Ok(keys)
+    }
+
+    /// Scans the matrix and checks which keys are pressed.
+    ///
+    /// Every column in order is activated through the shift
+    /// registers, and then each row pin is tested; if it's active,
+    /// the key is marked as pressed.
+    pub fn get<S, P>(&mut self) -> Result<[[bool; CS]; RS], SpiMatrixError<S, P>>
+    where
+        SPI: SpiDevice<Error = S>,
+        R: InputPin<Error = P>,
+    {
+        self.get_with_delay(|| ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::cell::RefCell;
+    use core::convert::Infallible;
+    use embedded_hal::digital::ErrorType;
+
+    /// A switch matrix with diodes: an input reads the active level
+    /// when a pressed key links it to an active output.
+    struct Board<const R: usize, const C: usize> {
+        keys: [[bool; C]; R],
+        polarity: Polarity,
+        rows: [bool; R],
+        cols: [bool; C],
+    }
+
+    impl<const R: usize, const C: usize> Board<R, C> {
+        fn new(polarity: Polarity, keys: [[bool; C]; R]) -> RefCell<Self> {
+            let idle = polarity == Polarity::ActiveLow;
+            RefCell::new(Board {
+                keys,
+                polarity,
+                rows: [idle; R],
+                cols: [idle; C],
+            })
+        }
+    }
+
+    /// A pin of the board, `row` selecting the row or column pins.
+    struct Pin<'a, const R: usize, const C: usize> {
+        board: &'a RefCell<Board<R, C>>,
+        row: bool,
+        index: usize,
+    }
+
+    fn pins<'a, const R: usize, const C: usize, const N: usize>(
+        board: &'a RefCell<Board<R, C>>,
+        row: bool,
+    ) -> [Pin<'a, R, C>; N] {
+        core::array::from_fn(|index| Pin { board, row, index })
+    }
+
+    impl<const R: usize, const C: usize> ErrorType for Pin<'_, R, C> {
+        type Error = Infallible;
+    }
+
+    impl<const R: usize, const C: usize> OutputPin for Pin<'_, R, C> {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.set(false);
+            Ok(())
+        }
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.set(true);
+            Ok(())
+        }
+    }
+
+    impl<const R: usize, const C: usize> InputPin for Pin<'_, R, C> {
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            let b = self.board.borrow();
+            let active = b.polarity == Polarity::ActiveHigh;
+            let linked = if self.row {
+                (0..C).any(|j| b.keys[self.index][j] && b.cols[j] == active)
+            } else {
+                (0..R).any(|i| b.keys[i][self.index] && b.rows[i] == active)
+            };
+            Ok(linked == active)
+        }
+        fn is_low(&mut self) -> Result<bool, Infallible> {
+            self.is_high().map(|high| !high)
+        }
+    }
+
+    impl<const R: usize, const C: usize> Pin<'_, R, C> {
+        fn set(&mut self, level: bool) {
+            let mut b = self.board.borrow_mut();
+            if self.row {
+                b.rows[self.index] = level;
+            } else {
+                b.cols[self.index] = level;
+            }
+        }
+    }
+
+    const EXPECTED: [[bool; 3]; 2] = [[true, false, false], [false, true, true]];
+
+    #[test]
+    fn col2row() {
+        for polarity in [Polarity::ActiveLow, Polarity::ActiveHigh] {
+            let board = Board::new(polarity, EXPECTED);
+            let mut matrix =
+                Matrix::new_with_polarity(pins(&board, false), pins(&board, true), polarity)
+                    .unwrap();
+            assert_eq!(Ok(EXPECTED), matrix.get());
+        }
+    }
+
+    #[test]
+    fn row2col() {
+        for polarity in [Polarity::ActiveLow, Polarity::ActiveHigh] {
+            let board = Board::new(polarity, EXPECTED);
+            let mut matrix = Matrix::new_row2col_with_polarity(
+                pins(&board, false),
+                pins(&board, true),
+                polarity,
+            )
+            .unwrap();
+            assert_eq!(Ok(EXPECTED), matrix.get());
+        }
+    }
+
+    #[test]
+    fn direct_pin_active_high() {
+        let board = Board::new(Polarity::ActiveHigh, EXPECTED);
+        // the row pins are used as always active outputs
+        board.borrow_mut().rows = [true; 2];
+        let board = &board;
+        let pin = |index| {
+            Some(Pin {
+                board,
+                row: false,
+                index,
+            })
+        };
+        let mut matrix =
+            DirectPinMatrix::new_with_polarity([[pin(0), None, pin(1)]], Polarity::ActiveHigh)
+                .unwrap();
+        assert_eq!(Ok([[true, false, true]]), matrix.get());
+    }
+
+    /// A 74HC165 reading the columns of the board: the column levels
+    /// are loaded while the latch is low, and shifted out on the
+    /// rising edges of the clock.
+    struct Piso<'a> {
+        board: &'a RefCell<Board<2, 3>>,
+        levels: [bool; 3],
+        clk: bool,
+    }
+
+    enum PisoPin<'a, 'b> {
+        Clk(&'b RefCell<Piso<'a>>),
+        Latch(&'b RefCell<Piso<'a>>),
+        Data(&'b RefCell<Piso<'a>>),
+    }
+
+    impl ErrorType for PisoPin<'_, '_> {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for PisoPin<'_, '_> {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            match self {
+                PisoPin::Clk(piso) => piso.borrow_mut().clk = false,
+                PisoPin::Latch(piso) => {
+                    let mut piso = piso.borrow_mut();
+                    let board = piso.board;
+                    for (index, level) in piso.levels.iter_mut().enumerate() {
+                        *level = Pin {
+                            board,
+                            row: false,
+                            index,
+                        }
+                        .is_high()?;
+                    }
+                }
+                PisoPin::Data(_) => unreachable!(),
+            }
+            Ok(())
+        }
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            match self {
+                PisoPin::Clk(piso) => {
+                    let mut piso = piso.borrow_mut();
+                    if !piso.clk {
+                        piso.levels.rotate_left(1);
+                    }
+                    piso.clk = true;
+                }
+                PisoPin::Latch(_) => (),
+                PisoPin::Data(_) => unreachable!(),
+            }
+            Ok(())
+        }
+    }
+
+    impl InputPin for PisoPin<'_, '_> {
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            match self {
+                PisoPin::Data(piso) => Ok(piso.borrow().levels[0]),
+                _ => unreachable!(),
+            }
+        }
+        fn is_low(&mut self) -> Result<bool, Infallible> {
+            self.is_high().map(|high| !high)
+        }
+    }
+
+    /// Cascaded 74HC595 driving the columns of the board, latched at
+    /// the end of the SPI transaction: the first byte is shifted
+    /// through to the last register of the cascade.
+    struct Sipo<'a, const R: usize, const C: usize>(&'a RefCell<Board<R, C>>);
+
+    impl<const R: usize, const C: usize> embedded_hal::spi::ErrorType for Sipo<'_, R, C> {
+        type Error = Infallible;
+    }
+
+    impl<const R: usize, const C: usize> SpiDevice for Sipo<'_, R, C> {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+            for op in operations {
+                if let Operation::Write(bytes) = op {
+                    assert_eq!(C.div_ceil(8), bytes.len());
+                    let mut board = self.0.borrow_mut();
+                    for (j, level) in board.cols.iter_mut().enumerate() {
+                        let register = bytes[bytes.len() - 1 - j / 8];
+                        *level = register & 1 << (j % 8) != 0;
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn shift_register() {
+        for polarity in [Polarity::ActiveLow, Polarity::ActiveHigh] {
+            let board = Board::new(polarity, EXPECTED);
+            let piso = RefCell::new(Piso {
+                board: &board,
+                levels: [false; 3],
+                clk: false,
+            });
+            let mut matrix = ShiftRegisterMatrix::<_, _, _, _, 3, 2>::new_with_polarity(
+                PisoPin::Clk(&piso),
+                PisoPin::Latch(&piso),
+                PisoPin::Data(&piso),
+                pins(&board, true),
+                polarity,
+            )
+            .unwrap();
+            assert_eq!(Ok(EXPECTED), matrix.get());
+        }
+    }
+
+    #[test]
+    fn spi_shift_register() {
+        for polarity in [Polarity::ActiveLow, Polarity::ActiveHigh] {
+            let board = Board::new(polarity, EXPECTED);
+            let mut matrix = SpiShiftRegisterMatrix::new_with_polarity(
+                Sipo(&board),
+                pins(&board, true),
+                polarity,
+            )
+            .unwrap();
+            assert_eq!(Ok(EXPECTED), matrix.get());
+            let idle = polarity == Polarity::ActiveLow;
+            assert_eq!([idle; 3], board.borrow().cols);
+        }
+    }
+
+    #[test]
+    fn spi_shift_register_cascade() {
+        // 2 registers, the columns 8 and 9 being driven by the second
+        let mut keys = [[false; 10]; 2];
+        keys[0][1] = true;
+        keys[0][8] = true;
+        keys[1][0] = true;
+        keys[1][9] = true;
+        for polarity in [Polarity::ActiveLow, Polarity::ActiveHigh] {
+            let board = Board::new(polarity, keys);
+            let mut matrix = SpiShiftRegisterMatrix::new_with_polarity(
+                Sipo(&board),
+                pins(&board, true),
+                polarity,
+            )
+            .unwrap();
+            assert_eq!(Ok(keys), matrix.get());
+            let idle = polarity == Polarity::ActiveLow;
+            assert_eq!([idle; 10], board.borrow().cols);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! Mouse emulation: mouse keys and mouse HID device implementation.
+
+use crate::hid::{self, HidDevice, Protocol, ReportType, Subclass};
+
+/// A mouse key, i.e. a mouse button, a cursor movement or a scroll
+/// (see [`Action::Mouse`](crate::action::Action::Mouse)).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MouseKey {
+    /// The left button.
+    Btn1,
+    /// The right button.
+    Btn2,
+    /// The middle button.
+    Btn3,
+    /// The back button.
+    Btn4,
+    /// The forward button.
+    Btn5,
+    /// Move the cursor up.
+    MoveUp,
+    /// Move the cursor down.
+    MoveDown,
+    /// Move the cursor left.
+    MoveLeft,
+    /// Move the cursor right.
+    MoveRight,
+    /// Scroll up.
+    WheelUp,
+    /// Scroll down.
+    WheelDown,
+    /// Scroll left.
+    WheelLeft,
+    /// Scroll right.
+    WheelRight,
+}
+
+impl MouseKey {
+    /// Returns the bit of the button in the report, if the key is a
+    /// button.
+    pub fn button_bit(self) -> Option<u8> {
+        use MouseKey::*;
+        match self {
+            Btn1 => Some(1),
+            Btn2 => Some(1 << 1),
+            Btn3 => Some(1 << 2),
+            Btn4 => Some(1 << 3),
+            Btn5 => Some(1 << 4),
+            _ => None,
+        }
+    }
+}
+
+/// Configuration of the mouse keys.
+///
+/// Movements are done every `move_interval` ticks (usually
+/// milliseconds) while the key is held. The movement goes from
+/// `move_delta_min` to `move_delta_max` units, linearly, during the
+/// first `move_accel_time` ticks, allowing both precise and fast
+/// movements. Scrolls are done every `wheel_interval` ticks, one unit
+/// at a time.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MouseConfig {
+    /// The number of ticks between two movements.
+    pub move_interval: u16,
+    /// The movement when the key is pressed.
+    pub move_delta_min: u8,
+    /// The movement after `move_accel_time` ticks.
+    pub move_delta_max: u8,
+    /// The number of ticks needed to reach `move_delta_max`.
+    pub move_accel_time: u16,
+    /// The number of ticks between two scrolls.
+    pub wheel_interval: u16,
+}
+
+impl Default for MouseConfig {
+    fn default() -> Self {
+        Self {
+            move_interval: 16,
+            move_delta_min: 1,
+            move_delta_max: 20,
+            move_accel_time: 1000,
+            wheel_interval: 80,
+        }
+    }
+}
+
+impl MouseConfig {
+    /// Returns the movement of a key held since `since` ticks.
+    pub(crate) fn move_delta(&self, since: u16) -> i8 {
+        let min = self.move_delta_min as u32;
+        let max = (self.move_delta_max as u32).max(min);
+        let delta = if since >= self.move_accel_time {
+            max
+        } else {
+            min + (max - min) * since as u32 / self.move_accel_time as u32
+        };
+        delta.min(i8::MAX as u32) as i8
+    }
+}
+
+/// A mouse USB HID report.
+///
+/// The movements are relative to the previous report.
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MouseReport {
+    /// The pressed buttons, a bit per button.
+    pub buttons: u8,
+    /// The horizontal movement, positive to the right.
+    pub x: i8,
+    /// The vertical movement, positive to the bottom.
+    pub y: i8,
+    /// The vertical scroll, positive to the top.
+    pub wheel: i8,
+    /// The horizontal scroll, positive to the right.
+    pub pan: i8,
+}
+
+impl MouseReport {
+    /// Returns the bytes corresponding to the report.
+    ///
+    /// The movements and scrolls are clamped to `-127..=127`, the
+    /// range declared by the report descriptor.
+    pub fn as_bytes(&self) -> [u8; 5] {
+        [
+            self.buttons,
+            clamp(self.x) as u8,
+            clamp(self.y) as u8,
+            clamp(self.wheel) as u8,
+            clamp(self.pan) as u8,
+        ]
+    }
+
+    /// Returns `true` if the report contains a movement or a scroll.
+    pub fn has_motion(&self) -> bool {
+        self.x != 0 || self.y != 0 || self.wheel != 0 || self.pan != 0
+    }
+}
+
+/// Clamps a relative value to the logical range of the report
+/// descriptor, `-127..=127`.
+pub(crate) fn clamp(value: i8) -> i8 {
+    value.max(-i8::MAX)
+}
+
+#[rustfmt::skip]
+const REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop Ctrls)
+    0x09, 0x02,        // Usage (Mouse)
+    0xA1, 0x01,        // Collection (Application)
+    0x09, 0x01,        //   Usage (Pointer)
+    0xA1, 0x00,        //   Collection (Physical)
+    0x05, 0x09,        //     Usage Page (Button)
+    0x19, 0x01,        //     Usage Minimum (0x01)
+    0x29, 0x05,        //     Usage Maximum (0x05)
+    0x15, 0x00,        //     Logical Minimum (0)
+    0x25, 0x01,        //     Logical Maximum (1)
+    0x95, 0x05,        //     Report Count (5)
+    0x75, 0x01,        //     Report Size (1)
+    0x81, 0x02,        //     Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    0x95, 0x01,        //     Report Count (1)
+    0x75, 0x03,        //     Report Size (3)
+    0x81, 0x03,        //     Input (Const,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    0x05, 0x01,        //     Usage Page (Generic Desktop Ctrls)
+    0x09, 0x30,        //     Usage (X)
+    0x09, 0x31,        //     Usage (Y)
+    0x09, 0x38,        //     Usage (Wheel)
+    0x15, 0x81,        //     Logical Minimum (-127)
+    0x25, 0x7F,        //     Logical Maximum (127)
+    0x95, 0x03,        //     Report Count (3)
+    0x75, 0x08,        //     Report Size (8)
+    0x81, 0x06,        //     Input (Data,Var,Rel,No Wrap,Linear,Preferred State,No Null Position)
+    0x05, 0x0C,        //     Usage Page (Consumer)
+    0x0A, 0x38, 0x02,  //     Usage (AC Pan)
+    0x95, 0x01,        //     Report Count (1)
+    0x81, 0x06,        //     Input (Data,Var,Rel,No Wrap,Linear,Preferred State,No Null Position)
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+/// A mouse HID device.
+pub struct Mouse {
+    report: [u8; 5],
+}
+
+impl Default for Mouse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mouse {
+    /// Creates a new `Mouse` object.
+    pub fn new() -> Mouse {
+        Mouse {
+            report: MouseReport::default().as_bytes(),
+        }
+    }
+    /// Set the current mouse HID report. Returns `true` if it must be
+    /// sent, i.e. if it is modified or contains a movement.
+    pub fn set_mouse_report(&mut self, report: MouseReport) -> bool {
+        let bytes = report.as_bytes();
+        if bytes == self.report && !report.has_motion() {
+            false
+        } else {
+            self.report = bytes;
+            true
+        }
+    }
+}
+
+impl HidDevice for Mouse {
+    fn subclass(&self) -> Subclass {
+        Subclass::BootInterface
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::Mouse
+    }
+
+    fn max_packet_size(&self) -> u16 {
+        8
+    }
+
+    fn report_descriptor(&self) -> &[u8] {
+        REPORT_DESCRIPTOR
+    }
+
+    fn interface_name(&self) -> Option<&str> {
+        Some("Mouse")
+    }
+
+    fn get_report(&mut self, report_type: ReportType, _report_id: u8) -> Result<&[u8], hid::Error> {
+        match report_type {
+            ReportType::Input => Ok(&self.report),
+            _ => Err(hid::Error),
+        }
+    }
+
+    fn set_report(
+        &mut self,
+        _report_type: ReportType,
+        _report_id: u8,
+        _data: &[u8],
+    ) -> Result<(), hid::Error> {
+        Err(hid::Error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn move_delta() {
+        let config = MouseConfig {
+            move_interval: 10,
+            move_delta_min: 2,
+            move_delta_max: 12,
+            move_accel_time: 100,
+            wheel_interval: 50,
+        };
+        assert_eq!(2, config.move_delta(0));
+        assert_eq!(7, config.move_delta(50));
+        assert_eq!(12, config.move_delta(100));
+        assert_eq!(12, config.move_delta(u16::MAX));
+
+        // without acceleration
+        let config = MouseConfig {
+            move_accel_time: 0,
+            ..config
+        };
+        assert_eq!(12, config.move_delta(0));
+
+        // the maximum is at least the minimum, and at most i8::MAX
+        let config = MouseConfig {
+            move_delta_min: 200,
+            move_delta_max: 100,
+            ..config
+        };
+        assert_eq!(127, config.move_delta(0));
+    }
+
+    #[test]
+    fn as_bytes() {
+        let report = MouseReport {
+            buttons: 0b101,
+            x: 1,
+            y: -1,
+            wheel: 127,
+            pan: -127,
+        };
+        assert_eq!([0b101, 1, 0xff, 0x7f, 0x81], report.as_bytes());
+
+        // -128 is out of the logical range of the report descriptor
+        let report = MouseReport {
+            x: i8::MIN,
+            y: i8::MIN,
+            wheel: i8::MIN,
+            pan: i8::MIN,
+            ..MouseReport::default()
+        };
+        assert_eq!([0, 0x81, 0x81, 0x81, 0x81], report.as_bytes());
+    }
+}
@@ -0,0 +1,125 @@
+//! Raw HID device implementation.
+//!
+//! A vendor defined HID device exchanging fixed size packets with the
+//! host through an interrupt IN and an interrupt OUT endpoint. It
+//! doesn't need any driver on the host, and is typically used by a
+//! configuration tool. The usage page (0xFF60) and usage (0x61) are
+//! the ones used by QMK, allowing to reuse the existing host tools.
+
+use crate::hid::{self, HidDevice, Protocol, ReportType, Subclass};
+
+/// A trait to handle the packets received from the host.
+pub trait RawHidHandler {
+    /// Handles a packet received from the host.
+    ///
+    /// `response` is zeroed before the call. Returns `true` if
+    /// `response` must be sent back to the host.
+    fn handle(&mut self, request: &[u8], response: &mut [u8]) -> bool;
+}
+
+const fn report_descriptor(size: u8) -> [u8; 34] {
+    #[rustfmt::skip]
+    let descriptor = [
+        0x06, 0x60, 0xFF,  // Usage Page (Vendor Defined 0xFF60)
+        0x09, 0x61,        // Usage (0x61)
+        0xA1, 0x01,        // Collection (Application)
+        0x09, 0x62,        //   Usage (0x62)
+        0x15, 0x00,        //   Logical Minimum (0)
+        0x26, 0xFF, 0x00,  //   Logical Maximum (255)
+        0x95, size,        //   Report Count (size)
+        0x75, 0x08,        //   Report Size (8)
+        0x81, 0x02,        //   Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+        0x09, 0x63,        //   Usage (0x63)
+        0x15, 0x00,        //   Logical Minimum (0)
+        0x26, 0xFF, 0x00,  //   Logical Maximum (255)
+        0x95, size,        //   Report Count (size)
+        0x75, 0x08,        //   Report Size (8)
+        0x91, 0x02,        //   Output (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position,Non-volatile)
+        0xC0,              // End Collection
+    ];
+    descriptor
+}
+
+/// A raw HID device, exchanging packets of `N` bytes (usually 32 or
+/// 64, at most 64) with the host.
+///
+/// The packets received on the interrupt OUT endpoint are given to
+/// the handler, and its responses are queued on the interrupt IN
+/// endpoint.
+pub struct RawHid<H, const N: usize = 32> {
+    handler: H,
+    report_descriptor: [u8; 34],
+    response: [u8; N],
+}
+
+impl<H, const N: usize> RawHid<H, N> {
+    /// Creates a new `RawHid` object.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is 0 or greater than 64.
+    pub fn new(handler: H) -> Self {
+        assert!(N > 0 && N <= 64, "the packet size must be between 1 and 64");
+        RawHid {
+            handler,
+            report_descriptor: report_descriptor(N as u8),
+            response: [0; N],
+        }
+    }
+
+    /// Returns the underlying handler.
+    pub fn handler_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
+}
+
+impl<H: RawHidHandler, const N: usize> HidDevice for RawHid<H, N> {
+    fn subclass(&self) -> Subclass {
+        Subclass::None
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::None
+    }
+
+    fn max_packet_size(&self) -> u16 {
+        N as u16
+    }
+
+    fn report_descriptor(&self) -> &[u8] {
+        &self.report_descriptor
+    }
+
+    fn interface_name(&self) -> Option<&str> {
+        Some("Raw HID")
+    }
+
+    fn get_report(&mut self, report_type: ReportType, _report_id: u8) -> Result<&[u8], hid::Error> {
+        match report_type {
+            ReportType::Input => Ok(&self.response),
+            _ => Err(hid::Error),
+        }
+    }
+
+    fn set_report(
+        &mut self,
+        _report_type: ReportType,
+        _report_id: u8,
+        _data: &[u8],
+    ) -> Result<(), hid::Error> {
+        Err(hid::Error)
+    }
+
+    fn out_max_packet_size(&self) -> Option<u16> {
+        Some(N as u16)
+    }
+
+    fn out_report(&mut self, data: &[u8]) -> Option<&[u8]> {
+        self.response = [0; N];
+        if self.handler.handle(data, &mut self.response) {
+            Some(&self.response)
+        } else {
+            None
+        }
+    }
+}
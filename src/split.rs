@@ -0,0 +1,1002 @@
+//! Split keyboard link protocol.
+//!
+//! A split keyboard is made of two halves linked by a serial line,
+//! usually a UART. This module defines a framing of the messages
+//! exchanged by the halves, tolerating garbage and lost bytes, and a
+//! reliable link retransmitting the messages until they are
+//! acknowledged by the other half.
+//!
+//! A frame is 7 bytes long: a start byte, the kind of the message, a
+//! sequence number, 2 bytes of payload and a big endian CRC-16 of the
+//! 4 previous bytes.
+//!
+//! Usually, only one half (the primary half) is connected to the
+//! host. It receives the key events of the other half (the secondary
+//! half) and sends back its [`SharedState`], i.e. the active layers
+//! and the host LED state, kept up to date on the secondary half by a
+//! [`Replica`]. When the secondary half restarts, reported by
+//! [`Link::take_peer_restarted`], its replica is set again using
+//! [`SharedState::all_messages`].
+//!
+//! The events of both halves are merged in the coordinate space of
+//! the layout by a [`SplitConfig`].
+//!
+//! # Example
+//!
+//! ```
+//! use keyberon::layout::Event;
+//! use keyberon::split::{Link, Message};
+//!
+//! let mut left = Link::new(10);
+//! let mut right = Link::new(10);
+//! left.send(Message::Event(Event::Press(0, 3))).unwrap();
+//!
+//! // the frames written on the serial line by a half are read by the
+//! // other half, until the links are synchronised and the message is
+//! // acknowledged
+//! let mut received = None;
+//! while !left.is_idle() || !right.is_idle() {
+//!     while let Some(frame) = left.poll_frame() {
+//!         for &b in &frame {
+//!             received = received.or(right.receive(b));
+//!         }
+//!     }
+//!     while let Some(frame) = right.poll_frame() {
+//!         for &b in &frame {
+//!             left.receive(b);
+//!         }
+//!     }
+//! }
+//! assert_eq!(Some(Message::Event(Event::Press(0, 3))), received);
+//! ```
+
+use crate::keyboard::{self, Leds};
+use crate::layout::{Event, Layout};
+use heapless::{Deque, Vec};
+
+/// The first byte of a frame.
+pub const START_OF_FRAME: u8 = 0xB2;
+
+/// The size of a frame, in bytes.
+pub const FRAME_SIZE: usize = 7;
+
+/// The maximum number of messages waiting for an acknowledgment.
+pub const WINDOW_SIZE: usize = 8;
+
+const KIND_ACK: u8 = 0;
+const KIND_PRESS: u8 = 1;
+const KIND_RELEASE: u8 = 2;
+const KIND_LAYER: u8 = 3;
+const KIND_DEFAULT_LAYER: u8 = 4;
+const KIND_LEDS: u8 = 5;
+const KIND_SYNC: u8 = 6;
+const KIND_SYNC_ACK: u8 = 7;
+
+/// The payload of the synchronisation frames, making a corrupted
+/// frame mistaken for a synchronisation frame unlikely.
+const SYNC_MAGIC: [u8; 3] = *b"SYN";
+
+/// A message exchanged by the halves.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    /// A key matrix event.
+    Event(Event),
+    /// The current layer of the primary half.
+    Layer(u8),
+    /// The default layer of the primary half.
+    DefaultLayer(u8),
+    /// The host LED state, see [`SharedState::leds`].
+    Leds(u8),
+}
+
+/// A frame of the link protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frame {
+    /// A message, with its sequence number.
+    Message(u8, Message),
+    /// The acknowledgment of all the messages up to the given
+    /// sequence number.
+    Ack(u8),
+    /// The synchronisation request of a restarted half, numbering its
+    /// messages from 0.
+    Sync,
+    /// The acknowledgment of a synchronisation request.
+    SyncAck,
+}
+
+/// CRC-16/CCITT-FALSE: polynomial 0x1021, initial value 0xFFFF.
+///
+/// A CRC-8 is not enough: when bytes are lost or inserted, the
+/// decoder tries to decode a lot of misaligned frames, some of them
+/// passing a CRC-8 check.
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0xFFFF, |crc, &byte| {
+        (0..8).fold(crc ^ (byte as u16) << 8, |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+impl Frame {
+    /// Returns the bytes of the frame.
+    pub fn encode(self) -> [u8; FRAME_SIZE] {
+        let (kind, seq, a, b) = match self {
+            Frame::Ack(seq) => (KIND_ACK, seq, 0, 0),
+            Frame::Sync => (KIND_SYNC, SYNC_MAGIC[0], SYNC_MAGIC[1], SYNC_MAGIC[2]),
+            Frame::SyncAck => (KIND_SYNC_ACK, SYNC_MAGIC[0], SYNC_MAGIC[1], SYNC_MAGIC[2]),
+            Frame::Message(seq, Message::Event(Event::Press(i, j))) => (KIND_PRESS, seq, i, j),
+            Frame::Message(seq, Message::Event(Event::Release(i, j))) => (KIND_RELEASE, seq, i, j),
+            Frame::Message(seq, Message::Layer(layer)) => (KIND_LAYER, seq, layer, 0),
+            Frame::Message(seq, Message::DefaultLayer(layer)) => {
+                (KIND_DEFAULT_LAYER, seq, layer, 0)
+            }
+            Frame::Message(seq, Message::Leds(leds)) => (KIND_LEDS, seq, leds, 0),
+        };
+        let [crc0, crc1] = crc16(&[kind, seq, a, b]).to_be_bytes();
+        [START_OF_FRAME, kind, seq, a, b, crc0, crc1]
+    }
+
+    /// Decodes a frame. Returns `None` if the frame is invalid.
+    pub fn decode(bytes: &[u8; FRAME_SIZE]) -> Option<Self> {
+        let [start, kind, seq, a, b, crc0, crc1] = *bytes;
+        if start != START_OF_FRAME || crc16(&[kind, seq, a, b]) != u16::from_be_bytes([crc0, crc1])
+        {
+            return None;
+        }
+        match kind {
+            KIND_ACK => Some(Frame::Ack(seq)),
+            KIND_SYNC if [seq, a, b] == SYNC_MAGIC => Some(Frame::Sync),
+            KIND_SYNC_ACK if [seq, a, b] == SYNC_MAGIC => Some(Frame::SyncAck),
+            KIND_PRESS => Some(Frame::Message(seq, Message::Event(Event::Press(a, b)))),
+            KIND_RELEASE => Some(Frame::Message(seq, Message::Event(Event::Release(a, b)))),
+            KIND_LAYER => Some(Frame::Message(seq, Message::Layer(a))),
+            KIND_DEFAULT_LAYER => Some(Frame::Message(seq, Message::DefaultLayer(a))),
+            KIND_LEDS => Some(Frame::Message(seq, Message::Leds(a))),
+            _ => None,
+        }
+    }
+}
+
+/// A streaming frame decoder, fed byte by byte.
+#[derive(Debug, Default, Clone)]
+pub struct Decoder {
+    buf: [u8; FRAME_SIZE],
+    len: usize,
+}
+
+impl Decoder {
+    /// Creates a new decoder.
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; FRAME_SIZE],
+            len: 0,
+        }
+    }
+
+    /// Feeds a byte read on the serial line. Returns the decoded
+    /// frame, if any.
+    ///
+    /// The bytes preceding a start byte are ignored. When an invalid
+    /// frame is read, the decoding restarts at the next start byte of
+    /// the invalid frame, resynchronising the decoder on the stream.
+    pub fn feed(&mut self, byte: u8) -> Option<Frame> {
+        if self.len == 0 && byte != START_OF_FRAME {
+            return None;
+        }
+        self.buf[self.len] = byte;
+        self.len += 1;
+        if self.len < FRAME_SIZE {
+            return None;
+        }
+        let frame = Frame::decode(&self.buf);
+        if frame.is_some() {
+            self.len = 0;
+        } else {
+            match self.buf[1..].iter().position(|&b| b == START_OF_FRAME) {
+                Some(pos) => {
+                    self.buf.copy_within(pos + 1.., 0);
+                    self.len = FRAME_SIZE - pos - 1;
+                }
+                None => self.len = 0,
+            }
+        }
+        frame
+    }
+}
+
+/// A reliable link between the two halves.
+///
+/// The messages are numbered and retransmitted until acknowledged by
+/// the other half: when no acknowledgment is received during
+/// `timeout` ticks, all the unacknowledged messages are sent again.
+/// The received messages are delivered in order, exactly once.
+///
+/// A new link first synchronises with the other half, the messages
+/// being sent once the synchronisation is acknowledged. When a half
+/// restarts, the other half then numbers its messages from 0 again,
+/// sending its unacknowledged messages to the restarted half.
+///
+/// The same link is used to send and receive messages, each half
+/// owning one.
+pub struct Link {
+    decoder: Decoder,
+    pending: Deque<Message, WINDOW_SIZE>,
+    base: u8,
+    sent: usize,
+    since: u16,
+    timeout: u16,
+    expected: u8,
+    ack: bool,
+    syncing: bool,
+    sync_ack: bool,
+    peer_restarted: bool,
+}
+
+impl Link {
+    /// Creates a new link, retransmitting the messages after
+    /// `timeout` ticks without acknowledgment.
+    ///
+    /// The timeout must be greater than the round trip time of a
+    /// frame. A frame takes about 0.6 ms at 115200 bauds.
+    pub const fn new(timeout: u16) -> Self {
+        Self {
+            decoder: Decoder::new(),
+            pending: Deque::new(),
+            base: 0,
+            sent: 0,
+            since: 0,
+            timeout,
+            expected: 0,
+            ack: false,
+            syncing: true,
+            sync_ack: false,
+            peer_restarted: false,
+        }
+    }
+
+    /// Queues a message. Returns the message back if
+    /// [`WINDOW_SIZE`] messages are already waiting for an
+    /// acknowledgment.
+    pub fn send(&mut self, message: Message) -> Result<(), Message> {
+        self.pending.push_back(message)
+    }
+
+    /// Returns the next frame to write on the serial line, if any.
+    ///
+    /// It must be called until it returns `None`, after each call to
+    /// [`send`](Link::send), [`receive`](Link::receive) or
+    /// [`tick`](Link::tick).
+    pub fn poll_frame(&mut self) -> Option<[u8; FRAME_SIZE]> {
+        if self.sync_ack {
+            self.sync_ack = false;
+            return Some(Frame::SyncAck.encode());
+        }
+        if self.syncing {
+            // the synchronisation request is sent again on timeout,
+            // as a message
+            if self.sent > 0 {
+                return None;
+            }
+            self.sent = 1;
+            self.since = 0;
+            return Some(Frame::Sync.encode());
+        }
+        if self.ack {
+            self.ack = false;
+            return Some(Frame::Ack(self.expected.wrapping_sub(1)).encode());
+        }
+        let message = *self.pending.iter().nth(self.sent)?;
+        if self.sent == 0 {
+            self.since = 0;
+        }
+        let seq = self.base.wrapping_add(self.sent as u8);
+        self.sent += 1;
+        Some(Frame::Message(seq, message).encode())
+    }
+
+    /// Feeds a byte read on the serial line. Returns the received
+    /// message, if any.
+    pub fn receive(&mut self, byte: u8) -> Option<Message> {
+        match self.decoder.feed(byte)? {
+            Frame::Sync => {
+                // the other half restarted: its messages are numbered
+                // from 0, and the unacknowledged messages are sent
+                // again, numbered from 0
+                self.expected = 0;
+                self.ack = false;
+                self.sync_ack = true;
+                self.base = 0;
+                self.sent = 0;
+                self.peer_restarted = true;
+                None
+            }
+            Frame::SyncAck => {
+                if self.syncing {
+                    self.syncing = false;
+                    self.sent = 0;
+                }
+                None
+            }
+            // the frames numbered before the synchronisation are
+            // ignored
+            _ if self.syncing => None,
+            Frame::Ack(seq) => {
+                self.acknowledge(seq);
+                None
+            }
+            Frame::Message(seq, message) => {
+                // duplicated messages are acknowledged again, as the
+                // previous acknowledgment may have been lost
+                self.ack = true;
+                if seq != self.expected {
+                    return None;
+                }
+                self.expected = self.expected.wrapping_add(1);
+                Some(message)
+            }
+        }
+    }
+
+    /// A time event.
+    ///
+    /// This method must be called regularly, typically every
+    /// millisecond.
+    pub fn tick(&mut self) {
+        if self.sent == 0 {
+            return;
+        }
+        self.since = self.since.saturating_add(1);
+        if self.since >= self.timeout {
+            self.sent = 0;
+        }
+    }
+
+    /// Returns `true` if the other half started or restarted since
+    /// the last call, i.e. if its state is unknown.
+    ///
+    /// The primary half then sends the whole [`SharedState`] to the
+    /// secondary half, using
+    /// [`all_messages`](SharedState::all_messages).
+    pub fn take_peer_restarted(&mut self) -> bool {
+        core::mem::take(&mut self.peer_restarted)
+    }
+
+    /// Returns `true` if the link is synchronised and every message
+    /// has been acknowledged.
+    pub fn is_idle(&self) -> bool {
+        !self.syncing && self.pending.is_empty()
+    }
+
+    fn acknowledge(&mut self, seq: u8) {
+        let nb = seq.wrapping_sub(self.base) as usize + 1;
+        if nb > self.pending.len() {
+            // old acknowledgment
+            return;
+        }
+        for _ in 0..nb {
+            self.pending.pop_front();
+        }
+        self.base = self.base.wrapping_add(nb as u8);
+        self.sent = self.sent.saturating_sub(nb);
+        self.since = 0;
+    }
+}
+
+/// The state of the primary half, replicated on the secondary half.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SharedState {
+    /// The current layer, see [`Layout::current_layer`].
+    pub current_layer: u8,
+    /// The default layer, see [`Layout::default_layer`].
+    pub default_layer: u8,
+    /// The host LED state: num lock is bit 0, caps lock bit 1, scroll
+    /// lock bit 2, compose bit 3 and kana bit 4, see
+    /// [`Keyboard::leds_report`](crate::keyboard::Keyboard::leds_report).
+    pub leds: u8,
+}
+
+impl SharedState {
+    /// Returns the state of the primary half, given its layout and
+    /// its host LED state.
+    pub fn new<const C: usize, const R: usize, const L: usize, T, K>(
+        layout: &Layout<C, R, L, T, K>,
+        leds: u8,
+    ) -> Self
+    where
+        T: 'static,
+        K: 'static + Copy + PartialEq,
+    {
+        Self {
+            current_layer: layout.current_layer() as u8,
+            default_layer: layout.default_layer() as u8,
+            leds,
+        }
+    }
+
+    /// Returns the messages updating a replica of the `previous`
+    /// state to this state.
+    ///
+    /// A replica whose state is unknown, as at the startup or the
+    /// restart of the secondary half (see
+    /// [`Link::take_peer_restarted`]), is synchronised using
+    /// [`all_messages`](SharedState::all_messages).
+    pub fn messages(&self, previous: &SharedState) -> Vec<Message, 3> {
+        self.all_messages()
+            .into_iter()
+            .zip(previous.all_messages())
+            .filter(|(new, old)| new != old)
+            .map(|(new, _)| new)
+            .collect()
+    }
+
+    /// Returns the messages setting a replica to this state.
+    pub fn all_messages(&self) -> Vec<Message, 3> {
+        Vec::from_slice(&[
+            Message::Layer(self.current_layer),
+            Message::DefaultLayer(self.default_layer),
+            Message::Leds(self.leds),
+        ])
+        .unwrap()
+    }
+}
+
+/// A replica of the state of the primary half, kept up to date on
+/// the secondary half using the received messages.
+///
+/// # Example
+///
+/// ```
+/// use keyberon::split::{Message, Replica};
+///
+/// let mut replica = Replica::new();
+/// assert!(replica.update(Message::Layer(2)));
+/// assert!(!replica.update(Message::Layer(2)));
+/// assert_eq!(2, replica.state().current_layer);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Replica {
+    state: SharedState,
+}
+
+impl Replica {
+    /// Creates a new replica, in the default state.
+    pub const fn new() -> Self {
+        Self {
+            state: SharedState {
+                current_layer: 0,
+                default_layer: 0,
+                leds: 0,
+            },
+        }
+    }
+
+    /// Updates the replica with a received message. Returns `true`
+    /// if the state is modified. The key events are ignored.
+    pub fn update(&mut self, message: Message) -> bool {
+        let old = self.state;
+        match message {
+            Message::Event(_) => (),
+            Message::Layer(layer) => self.state.current_layer = layer,
+            Message::DefaultLayer(layer) => self.state.default_layer = layer,
+            Message::Leds(leds) => self.state.leds = leds,
+        }
+        old != self.state
+    }
+
+    /// Returns the replicated state.
+    pub fn state(&self) -> &SharedState {
+        &self.state
+    }
+
+    /// Calls the [`Leds`] callbacks with the replicated host LED
+    /// state, allowing the secondary half to drive its indicators.
+    pub fn update_leds(&self, leds: &mut impl Leds) {
+        keyboard::set_leds(leds, self.state.leds);
+    }
+}
+
+/// A half of a split keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// The left half.
+    Left,
+    /// The right half.
+    Right,
+}
+
+/// The position of the matrix of a half in the coordinate space of
+/// the layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HalfConfig {
+    rows: u8,
+    cols: u8,
+    row_offset: u8,
+    col_offset: u8,
+    mirrored: bool,
+}
+
+impl HalfConfig {
+    /// Creates the configuration of a half with a matrix of `rows`
+    /// rows and `cols` columns, at the top left of the layout.
+    pub const fn new(rows: u8, cols: u8) -> Self {
+        Self {
+            rows,
+            cols,
+            row_offset: 0,
+            col_offset: 0,
+            mirrored: false,
+        }
+    }
+    /// Sets the column of the layout of the first column of the
+    /// matrix.
+    pub const fn col_offset(mut self, col_offset: u8) -> Self {
+        self.col_offset = col_offset;
+        self
+    }
+    /// Sets the row of the layout of the first row of the matrix.
+    pub const fn row_offset(mut self, row_offset: u8) -> Self {
+        self.row_offset = row_offset;
+        self
+    }
+    /// Mirrors the columns of the matrix: its first column is the
+    /// last one in the layout. It is typically used for the right
+    /// half of a reversible PCB.
+    pub const fn mirrored(mut self) -> Self {
+        self.mirrored = true;
+        self
+    }
+    /// Transforms the coordinates of an event of the matrix into the
+    /// coordinates of the layout. Returns `None` if the event is out
+    /// of the matrix, or if its coordinates in the layout overflow.
+    pub fn transform(&self, event: Event) -> Option<Event> {
+        let (i, j) = event.coord();
+        if i >= self.rows || j >= self.cols {
+            return None;
+        }
+        let j = if self.mirrored { self.cols - 1 - j } else { j };
+        let i = i.checked_add(self.row_offset)?;
+        let j = j.checked_add(self.col_offset)?;
+        Some(event.transform(|_, _| (i, j)))
+    }
+    /// Returns `true` if this half overlaps the `other` half in the
+    /// layout.
+    const fn overlaps(&self, other: &HalfConfig) -> bool {
+        let (row, col) = (self.row_offset as usize, self.col_offset as usize);
+        let (other_row, other_col) = (other.row_offset as usize, other.col_offset as usize);
+        row < other_row + other.rows as usize
+            && other_row < row + self.rows as usize
+            && col < other_col + other.cols as usize
+            && other_col < col + self.cols as usize
+    }
+}
+
+/// A declarative configuration of a split keyboard, merging the
+/// matrices of both halves in the coordinate space of the layout.
+///
+/// # Example
+///
+/// A 5x6 left half and a 5x7 right half, using the same reversible
+/// PCB design, giving a 5x13 layout:
+///
+/// ```
+/// use keyberon::layout::Event;
+/// use keyberon::split::{HalfConfig, Side, SplitConfig};
+///
+/// const SPLIT: SplitConfig = SplitConfig::new(
+///     HalfConfig::new(5, 6),
+///     HalfConfig::new(5, 7).col_offset(6).mirrored(),
+/// );
+/// type Layers = keyberon::layout::Layers<{ SPLIT.cols() }, { SPLIT.rows() }, 2>;
+///
+/// assert_eq!(13, SPLIT.cols());
+/// assert_eq!(
+///     Some(Event::Press(1, 12)),
+///     SPLIT.transform(Side::Right, Event::Press(1, 0)),
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplitConfig {
+    left: HalfConfig,
+    right: HalfConfig,
+}
+
+impl SplitConfig {
+    /// Creates a new split configuration.
+    pub const fn new(left: HalfConfig, right: HalfConfig) -> Self {
+        Self { left, right }
+    }
+    /// Returns the configuration of a half.
+    pub const fn half(&self, side: Side) -> &HalfConfig {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right,
+        }
+    }
+    /// Returns the number of rows of the layout.
+    pub const fn rows(&self) -> usize {
+        let left = self.left.row_offset as usize + self.left.rows as usize;
+        let right = self.right.row_offset as usize + self.right.rows as usize;
+        if left > right {
+            left
+        } else {
+            right
+        }
+    }
+    /// Returns the number of columns of the layout.
+    pub const fn cols(&self) -> usize {
+        let left = self.left.col_offset as usize + self.left.cols as usize;
+        let right = self.right.col_offset as usize + self.right.cols as usize;
+        if left > right {
+            left
+        } else {
+            right
+        }
+    }
+    /// Returns `true` if both halves fit in a layout of `cols`
+    /// columns and `rows` rows, i.e. in `Layers<cols, rows, _>`,
+    /// without overlapping.
+    ///
+    /// It is typically checked at compile time:
+    ///
+    /// ```
+    /// use keyberon::split::{HalfConfig, SplitConfig};
+    ///
+    /// const SPLIT: SplitConfig = SplitConfig::new(
+    ///     HalfConfig::new(4, 6),
+    ///     HalfConfig::new(4, 6).col_offset(6),
+    /// );
+    /// const _: () = assert!(SPLIT.is_valid_for(12, 4));
+    /// ```
+    pub const fn is_valid_for(&self, cols: usize, rows: usize) -> bool {
+        self.cols() <= cols && self.rows() <= rows && !self.left.overlaps(&self.right)
+    }
+    /// Transforms the coordinates of an event of the matrix of a half
+    /// into the coordinates of the layout. Returns `None` if the
+    /// event is out of the matrix, or if its coordinates in the
+    /// layout overflow.
+    pub fn transform(&self, side: Side, event: Event) -> Option<Event> {
+        self.half(side).transform(event)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use std::vec::Vec;
+
+    /// A serial line dropping, corrupting and inserting bytes.
+    struct LossyChannel {
+        seed: u32,
+    }
+
+    impl LossyChannel {
+        fn random(&mut self) -> u32 {
+            self.seed = self.seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            self.seed >> 16
+        }
+
+        fn transmit(&mut self, bytes: &[u8], to: &mut Link, received: &mut Vec<Message>) {
+            for &byte in bytes {
+                let byte = match self.random() % 100 {
+                    0..=2 => continue,
+                    3..=5 => byte ^ (1 << (self.random() % 8)),
+                    6..=8 => {
+                        received.extend(to.receive(START_OF_FRAME));
+                        byte
+                    }
+                    _ => byte,
+                };
+                received.extend(to.receive(byte));
+            }
+        }
+
+        /// Writes the frames of both links on the serial line, then
+        /// ticks them.
+        fn exchange(
+            &mut self,
+            left: &mut Link,
+            right: &mut Link,
+            received_left: &mut Vec<Message>,
+            received_right: &mut Vec<Message>,
+        ) {
+            while let Some(frame) = left.poll_frame() {
+                self.transmit(&frame, right, received_right);
+            }
+            while let Some(frame) = right.poll_frame() {
+                self.transmit(&frame, left, received_left);
+            }
+            left.tick();
+            right.tick();
+        }
+    }
+
+    fn event(i: u16) -> Message {
+        let (i, j) = ((i / 7) as u8, (i % 7) as u8);
+        if i % 2 == 0 {
+            Message::Event(Event::Press(i, j))
+        } else {
+            Message::Event(Event::Release(i, j))
+        }
+    }
+
+    #[test]
+    fn frame() {
+        let frame = Frame::Message(42, Message::Event(Event::Release(3, 4)));
+        let bytes = frame.encode();
+        assert_eq!(START_OF_FRAME, bytes[0]);
+        assert_eq!(Some(frame), Frame::decode(&bytes));
+        for i in 1..FRAME_SIZE {
+            let mut corrupted = bytes;
+            corrupted[i] ^= 0x10;
+            assert_eq!(None, Frame::decode(&corrupted));
+        }
+    }
+
+    #[test]
+    fn resynchronise() {
+        let mut decoder = Decoder::new();
+        let frame = Frame::Message(1, Message::Event(Event::Press(0, 1)));
+        let mut stream = std::vec![0x12, START_OF_FRAME, 0x34, START_OF_FRAME];
+        stream.extend_from_slice(&frame.encode());
+        stream.extend_from_slice(&frame.encode()[..3]);
+        stream.extend_from_slice(&Frame::Ack(3).encode());
+        let frames: Vec<_> = stream.iter().filter_map(|&b| decoder.feed(b)).collect();
+        assert_eq!(std::vec![frame, Frame::Ack(3)], frames);
+    }
+
+    #[test]
+    fn lossy_channel() {
+        const NB: u16 = 600;
+        let mut channel = LossyChannel { seed: 42 };
+        let mut left = Link::new(10);
+        let mut right = Link::new(10);
+        let (mut to_left, mut to_right) = (0, 0);
+        let mut received_left = Vec::new();
+        let mut received_right = Vec::new();
+        for _ in 0..20_000 {
+            if to_right < NB && left.send(event(to_right)).is_ok() {
+                to_right += 1;
+            }
+            if to_left < NB && right.send(event(to_left)).is_ok() {
+                to_left += 1;
+            }
+            channel.exchange(
+                &mut left,
+                &mut right,
+                &mut received_left,
+                &mut received_right,
+            );
+        }
+        assert!(left.is_idle());
+        assert!(right.is_idle());
+        let expected: Vec<_> = (0..NB).map(event).collect();
+        assert_eq!(expected, received_right);
+        assert_eq!(expected, received_left);
+    }
+
+    #[test]
+    fn restart() {
+        const NB: u16 = 600;
+        let mut channel = LossyChannel { seed: 42 };
+        let mut left = Link::new(10);
+        let mut right = Link::new(10);
+        let (mut to_left, mut to_right) = (0, 0);
+        let mut received_left = Vec::new();
+        let mut received_right = Vec::new();
+        let mut received_before_restart = Vec::new();
+        for t in 0..20_000 {
+            if t == 1000 {
+                // the right half restarts, losing its unacknowledged
+                // messages, and sends its messages from the start
+                right = Link::new(10);
+                to_left = 0;
+                received_before_restart = core::mem::take(&mut received_right);
+            }
+            if to_right < NB && left.send(event(to_right)).is_ok() {
+                to_right += 1;
+            }
+            if to_left < NB && right.send(event(to_left)).is_ok() {
+                to_left += 1;
+            }
+            channel.exchange(
+                &mut left,
+                &mut right,
+                &mut received_left,
+                &mut received_right,
+            );
+        }
+        assert!(left.is_idle());
+        assert!(right.is_idle());
+        let expected: Vec<_> = (0..NB).map(event).collect();
+
+        // the messages sent before and after the restart are received
+        let before = received_left.len() - expected.len();
+        assert!(0 < before && before < expected.len());
+        assert_eq!(expected[..before], received_left[..before]);
+        assert_eq!(expected[..], received_left[before..]);
+
+        // the unacknowledged messages are sent again to the restarted
+        // half
+        let before = received_before_restart.len();
+        let after = expected.len() - received_right.len();
+        assert!(0 < after && after <= before && before < expected.len());
+        assert_eq!(expected[..before], received_before_restart[..]);
+        assert_eq!(expected[after..], received_right[..]);
+    }
+
+    #[test]
+    fn replica() {
+        let mut leds = std::vec::Vec::new();
+        struct Recorder<'a>(&'a mut std::vec::Vec<(&'static str, bool)>);
+        impl Leds for Recorder<'_> {
+            fn caps_lock(&mut self, status: bool) {
+                self.0.push(("caps_lock", status));
+            }
+        }
+
+        let mut link_primary = Link::new(10);
+        let mut link_secondary = Link::new(10);
+        let mut replica = Replica::new();
+        let old = SharedState::default();
+        let new = SharedState {
+            current_layer: 2,
+            default_layer: 0,
+            leds: 0b10,
+        };
+        assert_eq!(
+            &[Message::Layer(2), Message::Leds(0b10)],
+            new.messages(&old).as_slice()
+        );
+        assert!(new.messages(&new).is_empty());
+        for message in new.messages(&old) {
+            link_primary.send(message).unwrap();
+        }
+        let mut modified = false;
+        while !link_primary.is_idle() || !link_secondary.is_idle() {
+            while let Some(frame) = link_primary.poll_frame() {
+                for &b in &frame {
+                    if let Some(message) = link_secondary.receive(b) {
+                        modified |= replica.update(message);
+                    }
+                }
+            }
+            while let Some(frame) = link_secondary.poll_frame() {
+                for &b in &frame {
+                    link_primary.receive(b);
+                }
+            }
+        }
+        assert!(modified);
+        assert_eq!(&new, replica.state());
+        replica.update_leds(&mut Recorder(&mut leds));
+        assert_eq!(std::vec![("caps_lock", true)], leds);
+        assert!(!replica.update(Message::Event(Event::Press(0, 0))));
+    }
+
+    #[test]
+    fn replica_restart() {
+        let mut channel = LossyChannel { seed: 42 };
+        let mut primary = Link::new(10);
+        let mut secondary = Link::new(10);
+        let mut replica = Replica::new();
+        let mut state = SharedState::default();
+        let mut sent = state;
+        let mut resync = false;
+        let mut received_primary = Vec::new();
+        let mut received_secondary = Vec::new();
+        for t in 0..5000 {
+            if t == 2000 {
+                // the secondary half restarts, its replica being back
+                // to the default state
+                secondary = Link::new(10);
+                replica = Replica::new();
+            }
+            // only the current layer is modified after the restart
+            state.current_layer = (t / 100 % 5) as u8;
+            if t < 1500 {
+                state.default_layer = (t / 300) as u8;
+                state.leds = (t / 200 % 8) as u8;
+            }
+
+            // the primary half sends the modifications of its state, or
+            // the whole state to a restarted secondary half
+            resync |= primary.take_peer_restarted();
+            let messages = match resync {
+                true => state.all_messages(),
+                false => state.messages(&sent),
+            };
+            if messages.iter().all(|&m| primary.send(m).is_ok()) {
+                sent = state;
+                resync = false;
+            }
+
+            channel.exchange(
+                &mut primary,
+                &mut secondary,
+                &mut received_primary,
+                &mut received_secondary,
+            );
+            for message in received_secondary.drain(..) {
+                replica.update(message);
+            }
+        }
+        assert!(primary.is_idle());
+        assert!(secondary.is_idle());
+        assert_eq!(4, state.default_layer);
+        assert_eq!(&state, replica.state());
+    }
+
+    #[test]
+    fn split_config() {
+        // a 5x6 left half and a 5x7 right half, the right half using
+        // a mirrored matrix
+        const SPLIT: SplitConfig = SplitConfig::new(
+            HalfConfig::new(5, 6),
+            HalfConfig::new(5, 7).col_offset(6).mirrored(),
+        );
+        assert_eq!(5, SPLIT.rows());
+        assert_eq!(13, SPLIT.cols());
+        let left = |e| SPLIT.transform(Side::Left, e);
+        let right = |e| SPLIT.transform(Side::Right, e);
+        assert_eq!(Some(Event::Press(0, 0)), left(Event::Press(0, 0)));
+        assert_eq!(Some(Event::Release(4, 5)), left(Event::Release(4, 5)));
+        assert_eq!(None, left(Event::Press(0, 6)));
+        assert_eq!(None, left(Event::Press(5, 0)));
+        assert_eq!(Some(Event::Press(0, 12)), right(Event::Press(0, 0)));
+        assert_eq!(Some(Event::Release(3, 6)), right(Event::Release(3, 6)));
+        assert_eq!(None, right(Event::Press(0, 7)));
+
+        // a thumb cluster below the main matrix
+        const THUMBS: SplitConfig = SplitConfig::new(
+            HalfConfig::new(4, 6),
+            HalfConfig::new(1, 3).row_offset(4).col_offset(2),
+        );
+        assert_eq!(5, THUMBS.rows());
+        assert_eq!(6, THUMBS.cols());
+        assert_eq!(
+            Some(Event::Press(4, 4)),
+            THUMBS.transform(Side::Right, Event::Press(0, 2))
+        );
+
+        // the coordinates overflowing a u8 are rejected
+        let far = HalfConfig::new(2, 10).row_offset(255).col_offset(250);
+        assert_eq!(
+            Some(Event::Press(255, 255)),
+            far.transform(Event::Press(0, 5))
+        );
+        assert_eq!(None, far.transform(Event::Press(0, 6)));
+        assert_eq!(None, far.transform(Event::Press(1, 0)));
+    }
+
+    #[test]
+    fn split_config_validation() {
+        const SPLIT: SplitConfig = SplitConfig::new(
+            HalfConfig::new(5, 6),
+            HalfConfig::new(5, 7).col_offset(6).mirrored(),
+        );
+        const _: () = assert!(SPLIT.is_valid_for(13, 5));
+        assert!(SPLIT.is_valid_for(14, 6));
+        assert!(!SPLIT.is_valid_for(12, 5));
+        assert!(!SPLIT.is_valid_for(13, 4));
+
+        // the halves must not overlap
+        let overlapping = SplitConfig::new(
+            HalfConfig::new(4, 6),
+            HalfConfig::new(1, 3).row_offset(3).col_offset(5),
+        );
+        assert!(!overlapping.is_valid_for(8, 4));
+        let thumbs = SplitConfig::new(
+            HalfConfig::new(4, 6),
+            HalfConfig::new(1, 3).row_offset(4).col_offset(5),
+        );
+        assert!(thumbs.is_valid_for(8, 5));
+        let stacked = SplitConfig::new(HalfConfig::new(4, 6), HalfConfig::new(4, 6));
+        assert!(!stacked.is_valid_for(6, 4));
+    }
+}
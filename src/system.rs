@@ -0,0 +1,148 @@
+//! System control (power, sleep, wake up) HID device implementation.
+//!
+//! `KeyCode::Power` and `KeyCode::MediaSleep` of the keyboard usage
+//! page are ignored by most of the operating systems. The system
+//! control usages of the generic desktop usage page are the standard
+//! way to power down, sleep or wake up the computer.
+
+use crate::hid::{self, HidDevice, Protocol, ReportType, Subclass};
+use crate::key_code::KeyCode;
+
+/// A system control usage (generic desktop usage page 0x01).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(u8)]
+pub enum SystemUsage {
+    /// Power down the computer.
+    PowerDown = 0x81,
+    /// Put the computer to sleep.
+    Sleep = 0x82,
+    /// Wake up the computer.
+    WakeUp = 0x83,
+}
+
+impl SystemUsage {
+    /// Returns the system usage corresponding to a key code of the
+    /// keyboard usage page, if any.
+    pub fn from_key_code(kc: KeyCode) -> Option<Self> {
+        match kc {
+            KeyCode::Power => Some(SystemUsage::PowerDown),
+            KeyCode::MediaSleep => Some(SystemUsage::Sleep),
+            _ => None,
+        }
+    }
+}
+
+/// A system control USB HID report.
+#[derive(Default, Debug, Clone, Eq, PartialEq)]
+pub struct SystemReport([u8; 1]);
+
+impl core::iter::FromIterator<SystemUsage> for SystemReport {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = SystemUsage>,
+    {
+        let mut res = Self::default();
+        for usage in iter {
+            res.pressed(usage);
+        }
+        res
+    }
+}
+
+impl SystemReport {
+    /// Returns the byte slice corresponding to the report.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Add the given usage to the report.
+    pub fn pressed(&mut self, usage: SystemUsage) {
+        self.0[0] |= 1 << (usage as u8 - SystemUsage::PowerDown as u8);
+    }
+}
+
+#[rustfmt::skip]
+const REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop Ctrls)
+    0x09, 0x80,        // Usage (Sys Control)
+    0xA1, 0x01,        // Collection (Application)
+    0x19, 0x81,        //   Usage Minimum (Sys Power Down)
+    0x29, 0x83,        //   Usage Maximum (Sys Wake Up)
+    0x15, 0x00,        //   Logical Minimum (0)
+    0x25, 0x01,        //   Logical Maximum (1)
+    0x95, 0x03,        //   Report Count (3)
+    0x75, 0x01,        //   Report Size (1)
+    0x81, 0x02,        //   Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    0x95, 0x01,        //   Report Count (1)
+    0x75, 0x05,        //   Report Size (5)
+    0x81, 0x03,        //   Input (Const,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    0xC0,              // End Collection
+];
+
+/// A system control HID device.
+pub struct System {
+    report: SystemReport,
+}
+
+impl Default for System {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl System {
+    /// Creates a new `System` object.
+    pub fn new() -> System {
+        System {
+            report: SystemReport::default(),
+        }
+    }
+    /// Set the current system control HID report. Returns `true` if
+    /// it is modified.
+    pub fn set_system_report(&mut self, report: SystemReport) -> bool {
+        if report == self.report {
+            false
+        } else {
+            self.report = report;
+            true
+        }
+    }
+}
+
+impl HidDevice for System {
+    fn subclass(&self) -> Subclass {
+        Subclass::None
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::None
+    }
+
+    fn max_packet_size(&self) -> u16 {
+        8
+    }
+
+    fn report_descriptor(&self) -> &[u8] {
+        REPORT_DESCRIPTOR
+    }
+
+    fn interface_name(&self) -> Option<&str> {
+        Some("System Control")
+    }
+
+    fn get_report(&mut self, report_type: ReportType, _report_id: u8) -> Result<&[u8], hid::Error> {
+        match report_type {
+            ReportType::Input => Ok(self.report.as_bytes()),
+            _ => Err(hid::Error),
+        }
+    }
+
+    fn set_report(
+        &mut self,
+        _report_type: ReportType,
+        _report_id: u8,
+        _data: &[u8],
+    ) -> Result<(), hid::Error> {
+        Err(hid::Error)
+    }
+}